@@ -0,0 +1,149 @@
+//! An allocator for finding leaked CF objects in tests.
+
+use std::alloc::{GlobalAlloc, Layout};
+use std::backtrace::Backtrace;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use crate::base::CFAllocator;
+use crate::cell::StrongCell;
+
+///An allocation that has not been freed yet.
+#[derive(Debug)]
+pub struct LiveAllocation {
+    ///Size in bytes, including a small per-allocation header
+    pub size: usize,
+    ///Where the allocation happened.  This is only captured if enabled with `RUST_BACKTRACE`,
+    /// see [Backtrace::capture].
+    pub backtrace: Backtrace,
+}
+
+#[derive(Default)]
+struct Allocations(Mutex<HashMap<usize, LiveAllocation>>);
+impl Allocations {
+    fn lock(&self) -> MutexGuard<'_, HashMap<usize, LiveAllocation>> {
+        //don't panic across the FFI boundary just because some test panicked
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+struct Tracking(Arc<Allocations>);
+unsafe impl GlobalAlloc for Tracking {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = std::alloc::alloc(layout);
+        if !ptr.is_null() {
+            self.0.lock().insert(ptr as usize, LiveAllocation { size: layout.size(), backtrace: Backtrace::capture() });
+        }
+        ptr
+    }
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.0.lock().remove(&(ptr as usize));
+        std::alloc::dealloc(ptr, layout)
+    }
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = std::alloc::realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            let mut allocations = self.0.lock();
+            allocations.remove(&(ptr as usize));
+            allocations.insert(new_ptr as usize, LiveAllocation { size: new_size, backtrace: Backtrace::capture() });
+        }
+        new_ptr
+    }
+}
+
+/**
+A [CFAllocator] that records which of its allocations are still live.
+
+Usually you want [assert_no_cf_leaks], which installs a tracker around a closure.  To use a tracker
+directly, pass [Self::allocator] to a `_in` create function, or install it with [CFAllocator::with_default].
+*/
+pub struct CFAllocationTracker {
+    allocations: Arc<Allocations>,
+    allocator: StrongCell<CFAllocator>,
+}
+impl CFAllocationTracker {
+    pub fn new() -> CFAllocationTracker {
+        let allocations = Arc::new(Allocations::default());
+        let allocator = CFAllocator::from_global_alloc(Tracking(allocations.clone()));
+        CFAllocationTracker { allocations, allocator }
+    }
+    ///The tracking allocator
+    pub fn allocator(&self) -> &CFAllocator {
+        &self.allocator
+    }
+    ///Number of allocations not yet freed
+    pub fn live_count(&self) -> usize {
+        self.allocations.lock().len()
+    }
+    ///Total size of allocations not yet freed
+    pub fn live_bytes(&self) -> usize {
+        self.allocations.lock().values().map(|a| a.size).sum()
+    }
+    ///Calls `f` with the allocations not yet freed.
+    pub fn with_live_allocations<R>(&self, f: impl FnOnce(&mut dyn Iterator<Item=&LiveAllocation>) -> R) -> R {
+        let allocations = self.allocations.lock();
+        f(&mut allocations.values())
+    }
+    ///Panics if any allocation has not been freed, listing the live allocations.
+    pub fn assert_no_leaks(&self) {
+        let allocations = self.allocations.lock();
+        if !allocations.is_empty() {
+            let bytes: usize = allocations.values().map(|a| a.size).sum();
+            let mut message = format!("{} CF allocations ({} bytes) leaked", allocations.len(), bytes);
+            for allocation in allocations.values() {
+                message += &format!("\n{} bytes allocated at:\n{}", allocation.size, allocation.backtrace);
+            }
+            panic!("{}", message);
+        }
+    }
+}
+impl Default for CFAllocationTracker {
+    fn default() -> Self {
+        CFAllocationTracker::new()
+    }
+}
+
+/**
+Runs `f` with a [CFAllocationTracker] as the default allocator, and panics if any object created
+in `f` is still alive afterwards.
+
+Objects created by this crate's create functions (like [crate::CFString::from_str] or [crate::CFData::copy_slice])
+on this thread are tracked.  Set `RUST_BACKTRACE=1` to include where each leaked object was allocated.
+*/
+pub fn assert_no_cf_leaks(f: impl FnOnce()) {
+    let tracker = CFAllocationTracker::new();
+    CFAllocator::with_default(tracker.allocator(), f);
+    tracker.assert_no_leaks();
+}
+
+#[cfg(test)] mod tests {
+    use super::{assert_no_cf_leaks, CFAllocationTracker};
+    use crate::{CFAllocator, CFArray, CFData, CFDictionary, CFString};
+
+    #[test] fn no_leaks() {
+        assert_no_cf_leaks(|| {
+            let string = CFString::from_str("hello, world");
+            let parts = string.split(&CFString::from_str(","));
+            let key = CFString::from_str("key");
+            let data = CFData::copy_slice(b"value");
+            let dictionary = CFDictionary::from_pairs(&[(&*key, &*data)]);
+            let array = CFArray::from_slice(&[&*dictionary]);
+            assert_eq!(parts.count(), 2);
+            assert_eq!(array.count(), 1);
+        });
+    }
+
+    #[test] #[should_panic(expected = "leaked")] fn leak() {
+        assert_no_cf_leaks(|| {
+            std::mem::forget(CFData::copy_slice(&[0; 100]));
+        });
+    }
+
+    #[test] fn counts() {
+        let tracker = CFAllocationTracker::new();
+        let data = CFAllocator::with_default(tracker.allocator(), || CFData::copy_slice(&[0; 100]));
+        assert!(tracker.live_count() > 0);
+        assert!(tracker.live_bytes() >= 100);
+        drop(data);
+        assert_eq!(tracker.live_count(), 0);
+    }
+}
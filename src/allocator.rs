@@ -0,0 +1,232 @@
+//!<CoreFoundation/CFBase.h>, allocator functions
+
+use std::alloc::{GlobalAlloc, Layout};
+use std::cell::RefCell;
+use std::ffi::c_void;
+use crate::base::{CFTypeID, CFTypeWithBaseType, CFAllocator, CFIndex, CFOptionFlags};
+use crate::cell::StrongCell;
+use crate::ownership::CreateRule;
+use crate::CFString;
+
+#[repr(C)]
+pub(crate) struct CFAllocatorContext {
+    pub(crate) version: CFIndex,
+    pub(crate) info: *mut c_void,
+    pub(crate) retain: Option<extern "C" fn(info: *const c_void) -> *const c_void>,
+    pub(crate) release: Option<extern "C" fn(info: *const c_void)>,
+    pub(crate) copy_description: Option<extern "C" fn(info: *const c_void) -> *const CFString>,
+    pub(crate) allocate: Option<extern "C" fn(alloc_size: CFIndex, hint: CFOptionFlags, info: *mut c_void) -> *mut c_void>,
+    pub(crate) reallocate: Option<extern "C" fn(ptr: *mut c_void, new_size: CFIndex, hint: CFOptionFlags, info: *mut c_void) -> *mut c_void>,
+    pub(crate) deallocate: Option<extern "C" fn(ptr: *mut c_void, info: *mut c_void)>,
+    pub(crate) preferred_size: Option<extern "C" fn(size: CFIndex, hint: CFOptionFlags, info: *mut c_void) -> CFIndex>,
+}
+
+extern "C" {
+    static kCFAllocatorNull: *const CFAllocator;
+    fn CFAllocatorGetTypeID() -> CFTypeID;
+    fn CFAllocatorCreate(allocator: *const CFAllocator, context: *mut CFAllocatorContext) -> CreateRule<CFAllocator>;
+}
+
+impl CFTypeWithBaseType for CFAllocator {
+    fn type_id() -> CFTypeID {
+        unsafe { CFAllocatorGetTypeID() }
+    }
+}
+
+///Drops the owner on the first deallocation
+extern "C" fn deallocate_owner<O>(_ptr: *mut c_void, info: *mut c_void) {
+    let owner = unsafe{ &mut *(info as *mut Option<O>) };
+    drop(owner.take());
+}
+///Frees the info when the allocator itself goes away.  If no deallocation happened, the owner is dropped here.
+extern "C" fn release_owner<O>(info: *const c_void) {
+    drop(unsafe{ Box::from_raw(info as *mut Option<O>) });
+}
+
+thread_local! {
+    ///Set by [CFAllocator::with_default]
+    static DEFAULT_ALLOCATOR: RefCell<Option<StrongCell<CFAllocator>>> = const { RefCell::new(None) };
+}
+
+///Restores the previous default allocator, even when unwinding
+struct RestoreDefault(Option<StrongCell<CFAllocator>>);
+impl Drop for RestoreDefault {
+    fn drop(&mut self) {
+        let previous = self.0.take();
+        DEFAULT_ALLOCATOR.with(|d| *d.borrow_mut() = previous);
+    }
+}
+
+///Allocations are prefixed with a header holding their size, since CF doesn't pass the size to `deallocate`.
+///
+/// The header is 16 bytes so the returned pointer keeps malloc's alignment, which CF assumes.
+const HEADER: usize = 16;
+
+///Returns the layout of an allocation with `size` usable bytes
+fn header_layout(size: usize) -> Layout {
+    Layout::from_size_align(size + HEADER, HEADER).unwrap()
+}
+
+extern "C" fn allocate_global<A: GlobalAlloc>(alloc_size: CFIndex, _hint: CFOptionFlags, info: *mut c_void) -> *mut c_void {
+    let alloc = unsafe{ &*(info as *const A) };
+    let size = alloc_size as usize;
+    unsafe {
+        let base = alloc.alloc(header_layout(size));
+        if base.is_null() {
+            return std::ptr::null_mut();
+        }
+        (base as *mut usize).write(size);
+        base.add(HEADER) as *mut c_void
+    }
+}
+extern "C" fn reallocate_global<A: GlobalAlloc>(ptr: *mut c_void, new_size: CFIndex, _hint: CFOptionFlags, info: *mut c_void) -> *mut c_void {
+    let alloc = unsafe{ &*(info as *const A) };
+    let new_size = new_size as usize;
+    unsafe {
+        let base = (ptr as *mut u8).sub(HEADER);
+        let old_size = (base as *const usize).read();
+        let new_base = alloc.realloc(base, header_layout(old_size), new_size + HEADER);
+        if new_base.is_null() {
+            return std::ptr::null_mut();
+        }
+        (new_base as *mut usize).write(new_size);
+        new_base.add(HEADER) as *mut c_void
+    }
+}
+extern "C" fn deallocate_global<A: GlobalAlloc>(ptr: *mut c_void, info: *mut c_void) {
+    let alloc = unsafe{ &*(info as *const A) };
+    unsafe {
+        let base = (ptr as *mut u8).sub(HEADER);
+        let size = (base as *const usize).read();
+        alloc.dealloc(base, header_layout(size));
+    }
+}
+extern "C" fn release_global<A: GlobalAlloc>(info: *const c_void) {
+    drop(unsafe{ Box::from_raw(info as *mut A) });
+}
+
+///Forwards to whatever `#[global_allocator]` the program uses
+struct RustGlobal;
+unsafe impl GlobalAlloc for RustGlobal {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        std::alloc::alloc(layout)
+    }
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        std::alloc::dealloc(ptr, layout)
+    }
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        std::alloc::realloc(ptr, layout, new_size)
+    }
+}
+
+impl CFAllocator {
+    ///Returns `allocator` as a pointer, with `None` meaning the default allocator.
+    ///
+    /// The default is the one installed by [Self::with_default] on this thread, if any, otherwise CF's default.
+    pub(crate) fn ptr_or_default(allocator: Option<&CFAllocator>) -> *const CFAllocator {
+        match allocator {
+            Some(allocator) => allocator,
+            None => DEFAULT_ALLOCATOR.with(|d| {
+                match d.borrow().as_ref() {
+                    Some(allocator) => &**allocator as *const CFAllocator,
+                    None => CFAllocator::null()
+                }
+            })
+        }
+    }
+    ///Runs `f` with `allocator` as the default for this crate's create functions on the current thread.
+    ///
+    /// This affects functions like [CFString::from_str] that don't take an allocator, or that are passed `None`.
+    /// Unlike `CFAllocatorSetDefault`, it does not change the allocator CF uses internally.
+    pub fn with_default<R>(allocator: &CFAllocator, f: impl FnOnce() -> R) -> R {
+        let allocator = unsafe{ StrongCell::retain_assuming_nonnull(allocator) };
+        let previous = DEFAULT_ALLOCATOR.with(|d| d.borrow_mut().replace(allocator));
+        let _restore = RestoreDefault(previous);
+        f()
+    }
+    ///Creates an allocator that allocates through Rust's global allocator, so that CF allocations
+    /// are visible to Rust allocation profilers.
+    pub fn from_rust_global() -> StrongCell<CFAllocator> {
+        CFAllocator::from_global_alloc(RustGlobal)
+    }
+    ///Creates an allocator that allocates through `alloc`.
+    ///
+    /// `alloc` is dropped when CF releases the allocator, which happens after every object allocated
+    /// with it is released.  This may happen on any thread.
+    ///
+    /// # Panics
+    /// If CF fails to allocate the object, see [allocation failure](crate#allocation-failure).
+    pub fn from_global_alloc<A: GlobalAlloc + Send + Sync + 'static>(alloc: A) -> StrongCell<CFAllocator> {
+        let info = Box::into_raw(Box::new(alloc));
+        let mut context = CFAllocatorContext {
+            version: 0,
+            info: info as *mut c_void,
+            retain: None,
+            release: Some(release_global::<A>),
+            copy_description: None,
+            allocate: Some(allocate_global::<A>),
+            reallocate: Some(reallocate_global::<A>),
+            deallocate: Some(deallocate_global::<A>),
+            preferred_size: None,
+        };
+        unsafe {
+            let raw = CFAllocatorCreate(CFAllocator::null(), &mut context);
+            raw.into_cell().expect("CFAllocatorCreate returned NULL")
+        }
+    }
+    ///`kCFAllocatorNull`, an allocator that does nothing.  This is useful as a deallocator for memory that should
+    /// not be freed.
+    pub fn allocator_null() -> *const CFAllocator {
+        unsafe { kCFAllocatorNull }
+    }
+    /**
+    Creates an allocator that can't allocate, and drops `owner` when asked to deallocate.
+
+    This is intended as the "bytes deallocator" for CF functions that take ownership of a buffer
+    (like `CFDataCreateWithBytesNoCopy`), where `owner` is the Rust value keeping the buffer alive.
+    `owner` is dropped exactly once, either on the first deallocation or when the allocator is released,
+    on whichever thread that happens.
+    */
+    pub(crate) fn dropping<O: Send + 'static>(owner: O) -> StrongCell<CFAllocator> {
+        let info = Box::into_raw(Box::new(Some(owner)));
+        let mut context = CFAllocatorContext {
+            version: 0,
+            info: info as *mut c_void,
+            retain: None,
+            release: Some(release_owner::<O>),
+            copy_description: None,
+            allocate: None,
+            reallocate: None,
+            deallocate: Some(deallocate_owner::<O>),
+            preferred_size: None,
+        };
+        unsafe {
+            let raw = CFAllocatorCreate(CFAllocator::null(), &mut context);
+            raw.into_cell().expect("CFAllocatorCreate returned NULL")
+        }
+    }
+}
+
+#[test] fn global_alloc() {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use crate::{CFData, CFTypeBehavior};
+    struct Counting(Arc<AtomicUsize>);
+    unsafe impl GlobalAlloc for Counting {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            std::alloc::alloc(layout)
+        }
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            std::alloc::dealloc(ptr, layout)
+        }
+    }
+    let count = Arc::new(AtomicUsize::new(0));
+    let allocator = CFAllocator::from_global_alloc(Counting(count.clone()));
+    let data = CFData::copy_slice_in(&[1; 1024], Some(&allocator));
+    assert!(count.load(Ordering::SeqCst) > 0);
+    assert_eq!(data.as_slice(), &[1; 1024]);
+    assert!(StrongCell::ptr_eq(&data.allocator(), &allocator));
+    let string = CFString::from_str_in("hello", Some(&CFAllocator::from_rust_global()));
+    assert_eq!(string.as_string(), "hello");
+}
@@ -1,44 +1,208 @@
 //!<CoreFoundation/CFArray.h>
 
-use crate::base::{CFType, CFTypeWithBaseType, CFTypeID, OpaqueCType, CFIndex, CFTypeAny};
+use std::ffi::c_void;
+use std::marker::PhantomData;
+use crate::base::{CFType, CFShared, CFSubtype, CFThreadSafe, CFMutableCopy, CFTypeWithBaseType, CFTypeID, OpaqueCType, CFIndex, CFTypeAny, CFAllocator};
+use crate::base::{cf_eq_hash, cf_thread_safe, cf_subtype};
+use crate::cell::{StrongCell, StrongMutCell};
+use crate::ownership::{CreateRule, GetRule};
+use crate::CFTypeBehavior;
 
+///An array of CF objects.
+///
+/// `T` is the element type.  CF arrays are heterogenous, so this defaults to [CFTypeAny].  Arrays with a more
+/// specific element type are "typed views", either returned by functions documented to produce
+/// such arrays, or obtained with [CFArray::cast_elements].
+///
+/// An array is [crate::CFThreadSafe] only if its element type is, so untyped arrays stay on one thread:
+/// ```compile_fail
+/// fn assert_send<S: Send>() {}
+/// assert_send::<core_foundationr::CFArray<core_foundationr::CFStringTokenizer>>();
+/// ```
 #[repr(C)]
-pub struct CFArray(OpaqueCType);
-impl CFType for CFArray {}
+pub struct CFArray<T: CFType = CFTypeAny>(OpaqueCType, PhantomData<T>);
+impl<T: CFType> CFType for CFArray<T> {}
+unsafe impl<T: CFType> CFShared for CFArray<T> {}
+cf_eq_hash!(impl<T> CFArray<T>);
+cf_thread_safe!(impl<T> CFArray<T>);
+cf_subtype!(impl<T> CFArray<T> => CFTypeAny);
+
+///A mutable array.  See cocoa docs for `CFMutableArrayRef`.
+///
+/// Mutating methods take `&mut self`, so these are usually held in a [StrongMutCell].
+#[repr(C)]
+pub struct CFMutableArray<T: CFType = CFTypeAny>(OpaqueCType, PhantomData<T>);
+impl<T: CFType> CFType for CFMutableArray<T> {}
+cf_eq_hash!(impl<T> CFMutableArray<T>);
+unsafe impl<T: CFType> CFSubtype<CFArray<T>> for CFMutableArray<T> {}
+unsafe impl<T: CFType> CFSubtype<CFTypeAny> for CFMutableArray<T> {}
+//may be moved between threads with its elements, but mutation isn't synchronized
+unsafe impl<T: CFThreadSafe> Send for CFMutableArray<T> {}
+
+///`CFArrayCallBacks`.  We only use CF's predefined callbacks, so the fields are opaque.
+#[repr(C)]
+struct CFArrayCallBacks([usize; 5]);
+
 extern "C" {
+    static kCFTypeArrayCallBacks: CFArrayCallBacks;
+    fn CFArrayCreateMutableCopy(allocator: *const CFAllocator, capacity: CFIndex, theArray: *const CFArray) -> CreateRule<CFMutableArray>;
+    fn CFArrayCreateMutable(allocator: *const CFAllocator, capacity: CFIndex, callBacks: *const CFArrayCallBacks) -> CreateRule<CFMutableArray>;
+    fn CFArrayAppendValue(theArray: *const CFMutableArray, value: *const c_void);
+    fn CFArrayCreate(allocator: *const CFAllocator, values: *const *const c_void, numValues: CFIndex, callBacks: *const CFArrayCallBacks) -> CreateRule<CFArray>;
     fn CFArrayGetTypeID() -> CFTypeID;
-    fn CFArrayGetValueAtIndex(theArray: * const CFArray, idx: CFIndex) -> *const CFTypeAny;
+    fn CFArrayGetValueAtIndex<'a>(theArray: &'a CFArray, idx: CFIndex) -> GetRule<'a, CFTypeAny>;
     fn CFArrayGetCount(theArray: *const CFArray) -> CFIndex;
 
 }
+//only the untyped array can be checked, since the check cannot see element types
 impl CFTypeWithBaseType for CFArray {
     fn type_id() -> CFTypeID {
         unsafe { CFArrayGetTypeID() }
     }
 }
-impl CFArray {
+impl<T: CFShared> CFArray<T> {
+    ///Creates an array of `values`, which are retained by the array.  See cocoa docs for `CFArrayCreate`.
+    ///
+    /// # Panics
+    /// If CF fails to allocate the object, see [allocation failure](crate#allocation-failure).
+    pub fn from_slice(values: &[&T]) -> StrongCell<CFArray<T>> {
+        CFArray::from_slice_in(values, None)
+    }
+    ///Like [Self::from_slice], using `allocator`, or the default allocator if `None`.
+    pub fn from_slice_in(values: &[&T], allocator: Option<&CFAllocator>) -> StrongCell<CFArray<T>> {
+        unsafe {
+            //&T is a thin pointer, so the slice is a C array of pointers
+            let raw = CFArrayCreate(CFAllocator::ptr_or_default(allocator), values.as_ptr() as *const *const c_void, values.len().try_into().unwrap(), &kCFTypeArrayCallBacks);
+            raw.assume_type::<CFArray<T>>().into_cell().expect("CFArrayCreate returned NULL")
+        }
+    }
+}
+impl<T: CFType> CFArray<T> {
     ///# Safety: access beyond the end of the array will throw an exception in objc, which is UB.
-    unsafe fn get_unchecked(&self, index: CFIndex) -> &CFTypeAny {
-        &*CFArrayGetValueAtIndex(self, index)
+    unsafe fn get_unchecked(&self, index: CFIndex) -> &T {
+        CFArrayGetValueAtIndex(self.as_untyped(), index).assume_type::<T>().get().expect("CFArray contains NULL")
+    }
+    ///Returns the number of elements, see cocoa docs for `CFArrayGetCount`.
+    pub fn count(&self) -> CFIndex {
+        unsafe{ CFArrayGetCount(self.as_untyped()) }
+    }
+    ///Returns the element at `index`, or `None` if the index is out of bounds.
+    pub fn get(&self, index: CFIndex) -> Option<&T> {
+        if index < 0 || index >= self.count() {
+            None
+        }
+        else {
+            Some(unsafe{ self.get_unchecked(index) })
+        }
     }
-    ///Return an iterator over the array elements, which may be of any type ([CFTypeAny])
-    pub fn iter(&self) -> impl Iterator<Item=&CFTypeAny> {
+    ///Return an iterator over the array elements
+    pub fn iter(&self) -> impl Iterator<Item=&T> {
         CFArrayIterator {
             array_ref: self,
             current_index: 0,
-            last_index: unsafe{ CFArrayGetCount(self)}
+            last_index: self.count()
+        }
+    }
+    ///Erases the element type.
+    pub fn as_untyped(&self) -> &CFArray {
+        unsafe{ &*(self as *const Self as *const CFArray) }
+    }
+}
+impl<T: CFType> CFMutableCopy for CFArray<T> {
+    type Mutable = CFMutableArray<T>;
+    fn mutable_copy_in(&self, allocator: Option<&CFAllocator>) -> StrongMutCell<CFMutableArray<T>> {
+        unsafe {
+            let raw = CFArrayCreateMutableCopy(CFAllocator::ptr_or_default(allocator), 0, self.as_untyped());
+            raw.assume_type::<CFMutableArray<T>>().into_cell().expect("CFArrayCreateMutableCopy returned NULL").assuming_mut()
+        }
+    }
+}
+impl<T: CFType> CFMutableCopy for CFMutableArray<T> {
+    type Mutable = CFMutableArray<T>;
+    fn mutable_copy_in(&self, allocator: Option<&CFAllocator>) -> StrongMutCell<CFMutableArray<T>> {
+        self.as_immutable().mutable_copy_in(allocator)
+    }
+}
+impl<T: CFType> CFMutableArray<T> {
+    ///Creates an empty mutable array, see cocoa docs for `CFArrayCreateMutable`.
+    ///
+    /// # Panics
+    /// If CF fails to allocate the object, see [allocation failure](crate#allocation-failure).
+    pub fn new() -> StrongMutCell<CFMutableArray<T>> {
+        Self::new_in(None)
+    }
+    ///Like [Self::new], using `allocator`, or the default allocator if `None`.
+    pub fn new_in(allocator: Option<&CFAllocator>) -> StrongMutCell<CFMutableArray<T>> {
+        unsafe {
+            let raw = CFArrayCreateMutable(CFAllocator::ptr_or_default(allocator), 0, &kCFTypeArrayCallBacks);
+            raw.assume_type::<CFMutableArray<T>>().into_cell().expect("CFArrayCreateMutable returned NULL").assuming_mut()
+        }
+    }
+    //the result must not escape the borrow, see CFShared
+    fn as_immutable(&self) -> &CFArray<T> {
+        unsafe{ &*(self as *const Self as *const CFArray<T>) }
+    }
+    ///Like [CFArray::count].
+    pub fn count(&self) -> CFIndex {
+        self.as_immutable().count()
+    }
+    ///Like [CFArray::get].  The element borrows the array, so it can't outlive a mutation.
+    pub fn get(&self, index: CFIndex) -> Option<&T> {
+        self.as_immutable().get(index)
+    }
+    ///Like [CFArray::iter].  The elements borrow the array, so they can't outlive a mutation.
+    pub fn iter(&self) -> impl Iterator<Item=&T> {
+        self.as_immutable().iter()
+    }
+}
+impl<T: CFShared> CFMutableArray<T> {
+    ///Appends `value`, which is retained by the array.  See cocoa docs for `CFArrayAppendValue`.
+    pub fn append(&mut self, value: &T) {
+        unsafe { CFArrayAppendValue(self as *const Self as *const CFMutableArray, value as *const T as *const c_void) }
+    }
+}
+impl CFArray {
+    ///Creates an array of values of any type, which are retained by the array.  See cocoa docs for `CFArrayCreate`.
+    ///
+    /// To mix types, pass trait objects, like `&[&*string as &dyn AsRef<CFTypeAny>, &*number]`.
+    pub fn from_values<V: AsRef<CFTypeAny>>(values: &[V]) -> StrongCell<CFArray> {
+        CFArray::from_values_in(values, None)
+    }
+    ///Like [Self::from_values], using `allocator`, or the default allocator if `None`.
+    pub fn from_values_in<V: AsRef<CFTypeAny>>(values: &[V], allocator: Option<&CFAllocator>) -> StrongCell<CFArray> {
+        let values: Vec<&CFTypeAny> = values.iter().map(|v| v.as_ref()).collect();
+        CFArray::from_slice_in(&values, allocator)
+    }
+    ///Views the array as an array of `R`, checking the type of every element.
+    ///
+    /// Returns `None` if any element has a different type.
+    pub fn cast_elements<R: CFTypeWithBaseType>(&self) -> Option<&CFArray<R>> {
+        if self.iter().all(|e| CFTypeBehavior::type_id(e) == R::type_id()) {
+            Some(unsafe{ self.assume_elements() })
+        }
+        else {
+            None
         }
     }
+    /**
+    Views the array as an array of `R` without checking.
+
+    # Safety
+    Every element must be of type `R`.
+    */
+    pub unsafe fn assume_elements<R: CFType>(&self) -> &CFArray<R> {
+        &*(self as *const Self as *const CFArray<R>)
+    }
 }
 
 ///Iterator type for CFArray
-struct CFArrayIterator<'a> {
-    array_ref: &'a CFArray,
+struct CFArrayIterator<'a, T: CFType> {
+    array_ref: &'a CFArray<T>,
     current_index: CFIndex,
     last_index: CFIndex
 }
-impl<'a> Iterator for CFArrayIterator<'a> {
-    type Item = &'a CFTypeAny;
+impl<'a, T: CFType> Iterator for CFArrayIterator<'a, T> {
+    type Item = &'a T;
 
     fn next(&mut self) -> Option<Self::Item> {
         if self.current_index == self.last_index {
@@ -52,7 +216,31 @@ impl<'a> Iterator for CFArrayIterator<'a> {
     }
 }
 
+#[cfg(test)] mod tests {
+    use crate::{CFString, CFMutableCopy, StrongCell};
+    use super::{CFArray, CFMutableArray};
 
+    #[test] fn mutable() {
+        let a = CFString::from_str("a");
+        let b = CFString::from_str("b");
+        let mut array = CFMutableArray::<CFString>::new();
+        array.append(&a);
+        let mut copy = array.mutable_copy();
+        copy.append(&b);
+        assert_eq!(array.count(), 1);
+        assert_eq!(copy.iter().map(|s| s.as_string()).collect::<Vec<_>>(), vec!["a", "b"]);
+        let immutable: StrongCell<CFArray<CFString>> = copy.upcast();
+        assert_eq!(immutable.get(1).unwrap().as_string(), "b");
+    }
 
-
-
+    #[test] fn from_values() {
+        use crate::{CFNumber, CFTypeAny};
+        let string = CFString::from_str("a");
+        let number = CFNumber::from_i64(1);
+        let array = CFArray::from_values(&[&*string as &dyn AsRef<CFTypeAny>, &number]);
+        assert_eq!(array.count(), 2);
+        assert!(array.cast_elements::<CFString>().is_none());
+        let strings = CFArray::from_values(&[string.clone(), string]);
+        assert!(strings.cast_elements::<CFString>().is_some());
+    }
+}
@@ -2,8 +2,11 @@
 
 
 use std::ffi::c_void;
+use std::marker::PhantomData;
 use std::os::raw::{c_long, c_ulong};
-use crate::cell::StrongCell;
+use crate::cell::{StrongCell, StrongMutCell};
+use crate::ownership::{CreateRule, GetRule};
+use crate::error::TypeMismatch;
 
 pub type CFOptionFlags = c_ulong;
 
@@ -14,19 +17,29 @@ pub type CFTypeID = c_ulong;
 /// In particular, this means they cannot be ZSTs, which might not have a valid address.
 /// Instead, we need to give them some non-ZST payload.  Note that this should never actually
 /// be constructed, because we don't use 'owned' values of these types.
+///
+/// The marker makes types `!Send` and `!Sync` by default; see [CFThreadSafe].
 #[repr(C)]
 #[derive(Debug)]
-pub(crate) struct OpaqueCType {
+pub struct OpaqueCType {
     //bool has alignment 1
-    _field: c_void
-
+    _field: c_void,
+    _not_thread_safe: PhantomData<*const c_void>,
 }
 
 #[repr(C)]
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
 pub struct CFRange {
     pub location: CFIndex,
     pub length: CFIndex
 }
+impl CFRange {
+    ///Panics unless the range is within `0..length`, for functions where CF doesn't check.
+    pub(crate) fn assert_in_bounds(self, length: CFIndex) {
+        let in_bounds = self.location >= 0 && self.length >= 0 && self.location.checked_add(self.length).is_some_and(|end| end <= length);
+        assert!(in_bounds, "range {:?} out of bounds", self);
+    }
+}
 
 ///We choose to represent `CFType`` as a trait.
 ///
@@ -34,32 +47,285 @@ pub struct CFRange {
 /// * [CFTypeAny] - concrete type, models a pointer to "any" type
 pub trait CFType {}
 
+/**
+`Self` is a subtype of `Super`, so every `Self` object is also a valid `Super`.
+
+For example, [CFMutableData](crate::CFMutableData) is a subtype of [CFData](crate::CFData), and every
+type is a subtype of [CFTypeAny].  [CFShared] subtypes upcast for free with `AsRef`, and [StrongCell::upcast]
+or [StrongMutCell::upcast] converts an owned object.
+
+Going the other way needs a check.  Use [StrongCell::try_cast] or [CFTypeBehavior::try_cast_ref] to downcast
+from [CFTypeAny].  CF gives mutable and immutable variants the same type ID, so there is no checked
+downcast to a mutable type; see [StrongCell::assuming_mut].
+
+# Safety
+Objects of `Self` must be valid objects of `Super`.
+*/
+pub unsafe trait CFSubtype<Super: CFType>: CFType {}
+
+///Implements [CFSubtype] and `AsRef`, like `cf_subtype!(CFData => CFTypeAny)`.
+///
+/// For generic types, list the generic parameters first, like `cf_subtype!(impl<T> CFArray<T> => CFTypeAny)`.
+/// Types that aren't [CFShared] implement [CFSubtype] alone, since `AsRef` would let a borrow escape.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! cf_subtype {
+    (impl<$($g:ident),*> $sub:ty => $sup:ty) => {
+        unsafe impl<$($g: $crate::CFType),*> $crate::CFSubtype<$sup> for $sub {}
+        impl<$($g: $crate::CFType),*> AsRef<$sup> for $sub {
+            fn as_ref(&self) -> &$sup {
+                unsafe{ &*(self as *const Self as *const $sup) }
+            }
+        }
+    };
+    ($sub:ty => $sup:ty) => {
+        $crate::cf_subtype!(impl<> $sub => $sup);
+    };
+}
+pub(crate) use crate::cf_subtype;
+
+/**
+Marks CF types whose objects may be shared, so a reference can be retained.
+
+APIs that retain a borrowed object, like [StrongCell::retain] or [CFArray::from_slice](crate::CFArray::from_slice),
+require this, as do the `AsRef` upcasts and [CFTypeBehavior::as_any].
+
+Mutable types like [CFMutableData](crate::CFMutableData) are not `CFShared`.  They are owned by a
+[StrongMutCell], and a reference borrowed from the cell must not outlive the borrow, since the object can be
+mutated afterwards.  Reads go through accessors on the mutable type instead, like [CFMutableData::as_slice](crate::CFMutableData::as_slice):
+```compile_fail
+use core_foundationr::{CFArray, CFMutableData};
+let mut data = CFMutableData::new();
+data.append(b"x");
+let array = CFArray::from_slice(&[&**data]);
+let slice = array.get(0).unwrap().as_slice();
+data.append(&[0; 1 << 20]);
+assert_eq!(slice[0], b'x');
+```
+
+# Safety
+Objects of the type must never be owned by a [StrongMutCell].
+*/
+pub unsafe trait CFShared: CFType {}
+
 #[repr(C)]
 pub struct CFString(OpaqueCType);
 impl CFType for CFString {}
+unsafe impl CFShared for CFString {}
+cf_eq_hash!(CFString);
+cf_thread_safe!(CFString);
+cf_subtype!(CFString => CFTypeAny);
 impl CFTypeWithBaseType for CFString {
     fn type_id() -> CFTypeID {
         unsafe {CFStringGetTypeID()}
     }
 }
 
+pub type CFHashCode = c_ulong;
+
+/**
+Implements [PartialEq], [Eq] and [Hash](std::hash::Hash) for CF types, using `CFEqual` and `CFHash`.
+
+For types that are generic over element types, list the generic parameters first, like `cf_eq_hash!(impl<T> CFArray<T>)`.
+*/
+#[doc(hidden)]
+#[macro_export]
+macro_rules! cf_eq_hash {
+    (impl<$($g:ident),*> $t:ty) => {
+        impl<$($g: $crate::CFType),*> PartialEq for $t {
+            fn eq(&self, other: &Self) -> bool {
+                $crate::__private::cf_equal(self, other)
+            }
+        }
+        impl<$($g: $crate::CFType),*> Eq for $t {}
+        impl<$($g: $crate::CFType),*> std::hash::Hash for $t {
+            fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+                $crate::__private::cf_hash(self).hash(state)
+            }
+        }
+    };
+    ($t:ty) => {
+        $crate::cf_eq_hash!(impl<> $t);
+    };
+}
+pub(crate) use crate::cf_eq_hash;
+
+/**
+Marks CF types whose objects are immutable and may be used from any thread.
+
+For these types, [StrongCell] is `Send` and `Sync`, and references to them are `Send` and `Sync`.
+
+# Safety
+Objects of the type must be safe to read, retain and release from several threads at once.
+*/
+pub unsafe trait CFThreadSafe: CFType + Send + Sync {}
+
+///Implements [CFThreadSafe], [Send] and [Sync], with the same syntax as [cf_eq_hash].
+///
+/// Generic parameters are bounded by [CFThreadSafe], so e.g. a container is only thread-safe if its elements are.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! cf_thread_safe {
+    (impl<$($g:ident),*> $t:ty) => {
+        unsafe impl<$($g: $crate::CFThreadSafe),*> Send for $t {}
+        unsafe impl<$($g: $crate::CFThreadSafe),*> Sync for $t {}
+        unsafe impl<$($g: $crate::CFThreadSafe),*> $crate::CFThreadSafe for $t {}
+    };
+    ($t:ty) => {
+        $crate::cf_thread_safe!(impl<> $t);
+    };
+}
+pub(crate) use crate::cf_thread_safe;
+
+/**
+Declares a CF type.
+
+```
+use core_foundationr::{cf_type, CFTypeBehavior, CFTypeWithBaseType, CFString, StrongCell};
+cf_type!(
+    ///A URL, see cocoa docs for `CFURLRef`.
+    pub struct CFURL: CFURLGetTypeID, thread_safe
+);
+let string: StrongCell<CFString> = CFString::from_str("not a URL");
+assert!(string.try_cast_ref::<CFURL>().is_err());
+```
+
+This generates
+* an opaque `#[repr(C)]` struct, used behind references like `&CFURL`,
+* [CFType], [CFShared], and [CFTypeWithBaseType] using the named `GetTypeID` function,
+* `PartialEq`, `Eq` and `Hash` using `CFEqual` and `CFHash`,
+* `Debug` using [CFTypeBehavior::description].
+
+Options follow the type ID function, in this order:
+* `thread_safe` implements [CFThreadSafe], for types whose objects are immutable.
+* `mutable pub struct CFMutableURL` declares a mutable subtype.  Both types are a [CFSubtype] of [CFTypeAny],
+  and the mutable type is a [CFSubtype] of the immutable one.
+  The subtype has no [CFTypeWithBaseType], because CF gives both the same type ID, so a checked cast can't tell them apart.
+  It isn't [CFShared] either, so it doesn't deref to the immutable type.  Instead, a private `as_immutable` method
+  lets the declaring module write read accessors, whose borrows are tied to the [StrongMutCell].
+  If the type is `thread_safe`, the subtype is `Send` but not `Sync`.
+* `unique` is for types that are mutable without a separate mutable type, and are created as a [StrongMutCell].
+  These aren't [CFShared], and can't be `thread_safe` or have a `mutable` subtype.
+*/
+#[macro_export]
+macro_rules! cf_type {
+    ($(#[$meta:meta])* $vis:vis struct $name:ident : $type_id:ident, unique $(,)?) => {
+        $crate::cf_type!(@define $(#[$meta])* $vis struct $name : $type_id);
+        unsafe impl $crate::CFSubtype<$crate::CFTypeAny> for $name {}
+    };
+    ($(#[$meta:meta])* $vis:vis struct $name:ident : $type_id:ident, thread_safe $(, mutable $(#[$mutable_meta:meta])* $mutable_vis:vis struct $mutable:ident)? $(,)?) => {
+        $crate::cf_type!(@define $(#[$meta])* $vis struct $name : $type_id);
+        $crate::cf_type!(@shared $name);
+        $crate::cf_thread_safe!($name);
+        $(
+            $crate::cf_type!(@mutable $name, $(#[$mutable_meta])* $mutable_vis struct $mutable);
+            //may be moved between threads, but mutation isn't synchronized
+            unsafe impl Send for $mutable {}
+        )?
+    };
+    ($(#[$meta:meta])* $vis:vis struct $name:ident : $type_id:ident $(, mutable $(#[$mutable_meta:meta])* $mutable_vis:vis struct $mutable:ident)? $(,)?) => {
+        $crate::cf_type!(@define $(#[$meta])* $vis struct $name : $type_id);
+        $crate::cf_type!(@shared $name);
+        $(
+            $crate::cf_type!(@mutable $name, $(#[$mutable_meta])* $mutable_vis struct $mutable);
+        )?
+    };
+    (@define $(#[$meta:meta])* $vis:vis struct $name:ident : $type_id:ident) => {
+        $(#[$meta])*
+        #[repr(C)]
+        $vis struct $name($crate::__private::OpaqueCType);
+        impl $crate::CFType for $name {}
+        $crate::cf_eq_hash!($name);
+        impl $crate::CFTypeWithBaseType for $name {
+            fn type_id() -> $crate::CFTypeID {
+                extern "C" {
+                    fn $type_id() -> $crate::CFTypeID;
+                }
+                unsafe { $type_id() }
+            }
+        }
+        $crate::cf_type!(@debug $name);
+    };
+    (@shared $name:ident) => {
+        unsafe impl $crate::CFShared for $name {}
+        $crate::cf_subtype!($name => $crate::CFTypeAny);
+    };
+    (@mutable $name:ident, $(#[$meta:meta])* $vis:vis struct $mutable:ident) => {
+        $(#[$meta])*
+        #[repr(C)]
+        $vis struct $mutable($crate::__private::OpaqueCType);
+        impl $crate::CFType for $mutable {}
+        $crate::cf_eq_hash!($mutable);
+        unsafe impl $crate::CFSubtype<$name> for $mutable {}
+        unsafe impl $crate::CFSubtype<$crate::CFTypeAny> for $mutable {}
+        impl $mutable {
+            //the result must not escape the borrow, see CFShared
+            #[allow(dead_code)]
+            fn as_immutable(&self) -> &$name {
+                unsafe{ &*(self as *const $mutable as *const $name) }
+            }
+        }
+        $crate::cf_type!(@debug $mutable);
+    };
+    (@debug $name:ident) => {
+        impl ::std::fmt::Debug for $name {
+            fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+                f.write_str(&$crate::CFTypeBehavior::description(self).as_string())
+            }
+        }
+    };
+}
+
+///Compares with `CFEqual`
+pub fn cf_equal<A: CFType + ?Sized, B: CFType + ?Sized>(a: &A, b: &B) -> bool {
+    unsafe { CFEqual(a as *const A as *const c_void, b as *const B as *const c_void) }
+}
+///Hashes with `CFHash`
+pub fn cf_hash<T: CFType + ?Sized>(t: &T) -> CFHashCode {
+    unsafe { CFHash(t as *const T as *const c_void) }
+}
+
 extern "C" {
     //*c_void in here is basically CFTypeRef (which is a trait in Rust)
-    fn CFCopyDescription(cf: *const c_void) -> *const CFString;
+    fn CFEqual(cf1: *const c_void, cf2: *const c_void) -> bool;
+    fn CFHash(cf: *const c_void) -> CFHashCode;
+    fn CFCopyDescription(cf: *const c_void) -> CreateRule<CFString>;
     fn CFGetTypeID(cf: *const c_void ) -> CFTypeID;
+    fn CFGetRetainCount(cf: *const c_void) -> CFIndex;
+    fn CFGetAllocator<'a>(cf: &'a c_void) -> GetRule<'a, CFAllocator>;
     fn CFStringGetTypeID() -> CFTypeID;
+    static kCFNull: *const CFNull;
 }
 
 pub trait CFTypeBehavior {
     ///Returns a (strong) pointer to the description
+    ///
+    /// # Panics
+    /// If CF fails to allocate the object, see [allocation failure](crate#allocation-failure).
     fn description(&self) -> StrongCell<CFString>;
     ///Finds the CoreFoundation type id
     fn type_id(&self) -> CFTypeID;
+    ///Returns the retain count, see cocoa docs for `CFGetRetainCount`.
+    ///
+    /// This is for debugging only.  Other threads, autorelease pools and CF internals may change the count at any time.
+    fn retain_count(&self) -> CFIndex;
+    ///Returns the allocator that created the object, see cocoa docs for `CFGetAllocator`.
+    fn allocator(&self) -> StrongCell<CFAllocator>;
+    ///Prints the description to stderr, like `CFShow`.
+    fn show(&self);
     ///Perform a checked cast to some other type.  The returned pointer
     /// has the same lifetime as the current pointer.
-    fn checked_cast<R: CFTypeWithBaseType>(&self) -> &R;
+    ///
+    /// # Panics
+    /// If the object has a different type.  See [Self::try_cast_ref] for a version that doesn't panic.
+    fn checked_cast<R: CFTypeWithBaseType>(&self) -> &R where Self: CFShared;
+    ///Perform a checked cast to some other type, returning an error if the object has a different type.
+    /// The returned pointer has the same lifetime as the current pointer.
+    fn try_cast_ref<R: CFTypeWithBaseType>(&self) -> Result<&R, TypeMismatch> where Self: CFShared;
     ///Erase to a raw pointer
     fn as_ptr(&self) -> *const c_void;
+    ///Upcast to [CFTypeAny], for APIs that accept any CF object.  See also [StrongCell::into_any].
+    fn as_any(&self) -> &CFTypeAny where Self: CFShared;
     ///Create a type from a raw pointer
     ///
     /// See also: [Self::from_ref]
@@ -72,6 +338,8 @@ pub trait CFTypeBehavior {
     ///
     /// If you do not know what you're doing, put the return value into a [StrongCell] right away to
     /// promote to the `'static` (e.g. runtime managed) lifetime.  Such use should be safe, at some additional performance cost.
+    ///
+    /// When binding a CF function, declare it to return [crate::CreateRule] or [crate::GetRule] instead.
     unsafe fn from_ptr(ptr: *const c_void) -> *const Self;
 
     ///Create a type from a reference
@@ -92,20 +360,42 @@ impl<T: CFType> CFTypeBehavior for T {
     fn description(&self) -> StrongCell<CFString> {
         let r1 = self.as_ptr();
         let raw = unsafe{ CFCopyDescription(r1) };
-        unsafe{ StrongCell::assuming_retained_nonnull(raw) }
+        raw.into_cell().expect("CFCopyDescription returned NULL")
     }
     fn type_id(&self) -> CFTypeID {
         unsafe { CFGetTypeID(self.as_ptr()) }
     }
+    fn retain_count(&self) -> CFIndex {
+        unsafe { CFGetRetainCount(self.as_ptr()) }
+    }
+    fn allocator(&self) -> StrongCell<CFAllocator> {
+        unsafe { CFGetAllocator(&*self.as_ptr()) }.retain().expect("CFGetAllocator returned NULL")
+    }
+    fn show(&self) {
+        eprintln!("{}", self.description().as_string())
+    }
+
+    fn checked_cast<R: CFTypeWithBaseType>(&self) -> &R where Self: CFShared {
+        self.try_cast_ref().unwrap_or_else(|e| panic!("{}", e))
+    }
 
-    fn checked_cast<R: CFTypeWithBaseType>(&self) -> &R {
-        assert_eq!(CFTypeBehavior::type_id(self), R::type_id());
-        unsafe{ &*R::from_ptr(self.as_ptr()) }
+    fn try_cast_ref<R: CFTypeWithBaseType>(&self) -> Result<&R, TypeMismatch> where Self: CFShared {
+        let actual = CFTypeBehavior::type_id(self);
+        let expected = R::type_id();
+        if actual == expected {
+            Ok(unsafe{ &*R::from_ptr(self.as_ptr()) })
+        }
+        else {
+            Err(TypeMismatch { expected, actual })
+        }
     }
 
     fn as_ptr(&self) -> *const c_void {
         self as *const Self as *const c_void
     }
+    fn as_any(&self) -> &CFTypeAny where Self: CFShared {
+        unsafe{ &*(self as *const Self as *const CFTypeAny) }
+    }
 
     unsafe fn from_ptr(ptr: *const c_void) -> *const Self {
         ptr as *const Self
@@ -115,6 +405,24 @@ impl<T: CFType> CFTypeBehavior for T {
     }
 }
 
+///Types that can be copied into a new, mutable object, like with `CFStringCreateMutableCopy`.
+///
+/// The copy is a new object (not a retain of the existing one).  For containers, the copy is shallow:
+/// the elements are retained, not copied.
+pub trait CFMutableCopy: CFType {
+    ///The type of the copy
+    type Mutable: CFType;
+    ///Copies into a new mutable object, using `allocator`, or the default allocator if `None`.
+    ///
+    /// # Panics
+    /// If CF fails to allocate the object, see [allocation failure](crate#allocation-failure).
+    fn mutable_copy_in(&self, allocator: Option<&CFAllocator>) -> StrongMutCell<Self::Mutable>;
+    ///Copies into a new mutable object.
+    fn mutable_copy(&self) -> StrongMutCell<Self::Mutable> {
+        self.mutable_copy_in(None)
+    }
+}
+
 ///These are types that have a "base type" (e.g. not polymorphic over underlying types).
 pub trait CFTypeWithBaseType: CFType {
     fn type_id() -> CFTypeID;
@@ -124,12 +432,41 @@ pub trait CFTypeWithBaseType: CFType {
 #[repr(C)]
 pub struct CFTypeAny(OpaqueCType);
 impl CFType for CFTypeAny {}
+unsafe impl CFShared for CFTypeAny {}
+cf_eq_hash!(CFTypeAny);
+impl AsRef<CFTypeAny> for CFTypeAny {
+    fn as_ref(&self) -> &CFTypeAny {
+        self
+    }
+}
+
+cf_type!(
+    ///The singleton null object, see cocoa docs for `kCFNull`.
+    ///
+    /// This is used to represent "no value" in containers that can't hold NULL.
+    pub struct CFNull: CFNullGetTypeID, thread_safe
+);
+impl CFNull {
+    ///Returns `kCFNull`
+    pub fn null() -> &'static CFNull {
+        unsafe { &*kCFNull }
+    }
+}
 
 #[repr(C)]
 pub struct CFAllocator(OpaqueCType);
+impl CFType for CFAllocator {}
+unsafe impl CFShared for CFAllocator {}
+cf_eq_hash!(CFAllocator);
+cf_thread_safe!(CFAllocator);
+cf_subtype!(CFAllocator => CFTypeAny);
 impl CFAllocator {
     ///note: CFAllocator is often null, so cannot be legally implemented with a reference
     pub fn null() -> *const CFAllocator { std::ptr::null() as *const CFAllocator }
 }
 
-pub type CFIndex = c_long;
\ No newline at end of file
+pub type CFIndex = c_long;
+
+///Returned as a location by search functions to indicate there was no match.
+#[allow(non_upper_case_globals)]
+pub const kCFNotFound: CFIndex = -1;
\ No newline at end of file
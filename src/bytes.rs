@@ -0,0 +1,56 @@
+//! Zero-copy conversions between [CFData] and [::bytes::Bytes], behind the `bytes` feature.
+
+use ::bytes::Bytes;
+use crate::cell::StrongCell;
+use crate::CFData;
+
+///Keeps the CF object retained for as long as the [Bytes] (or any clone of it) lives.
+struct CFDataOwner(StrongCell<CFData>);
+impl AsRef<[u8]> for CFDataOwner {
+    fn as_ref(&self) -> &[u8] {
+        self.0.as_slice()
+    }
+}
+
+///Wraps the bytes without copying.  The [Bytes] is kept alive until CF releases the data.
+impl From<Bytes> for StrongCell<CFData> {
+    fn from(bytes: Bytes) -> Self {
+        CFData::from_owner(bytes, None)
+    }
+}
+
+///Wraps the data without copying.  The data stays retained until the last [Bytes] referencing it is dropped.
+impl From<StrongCell<CFData>> for Bytes {
+    fn from(data: StrongCell<CFData>) -> Self {
+        Bytes::from_owner(CFDataOwner(data))
+    }
+}
+
+#[cfg(test)] mod tests {
+    use ::bytes::Bytes;
+    use crate::{CFData, CFTypeBehavior, StrongCell};
+
+    #[test] fn bytes_to_data() {
+        //the Bytes owns a retain on `inner`, so the retain count shows when it's dropped
+        let inner = CFData::copy_slice(b"hello world");
+        let bytes = Bytes::from(inner.clone());
+        let ptr = bytes.as_ptr();
+        let data: StrongCell<CFData> = bytes.clone().into();
+        assert_eq!(data.GetBytePtr(), ptr);
+        drop(bytes);
+        assert_eq!(inner.retain_count(), 2);
+        assert_eq!(data.as_slice(), b"hello world");
+        drop(data);
+        assert_eq!(inner.retain_count(), 1);
+    }
+
+    #[test] fn data_to_bytes() {
+        let data = CFData::copy_slice(b"hello world");
+        let ptr = data.GetBytePtr();
+        let bytes: Bytes = data.into();
+        assert_eq!(bytes.as_ptr(), ptr);
+        let slice = bytes.slice(6..);
+        drop(bytes);
+        assert_eq!(&slice[..], b"world");
+    }
+}
@@ -0,0 +1,204 @@
+//!<CoreFoundation/CFCalendar.h>
+
+use std::os::raw::{c_char, c_int};
+use crate::base::{CFAllocator, CFOptionFlags, CFRange, kCFNotFound};
+use crate::cell::{StrongCell, StrongMutCell};
+use crate::date::CFAbsoluteTime;
+use crate::ownership::CreateRule;
+use crate::time_zone::CFTimeZone;
+use crate::{cf_type, CFString};
+
+///A calendar unit, for [CFCalendar::range_of_unit] and friends.
+#[repr(transparent)]
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub struct CFCalendarUnit(CFOptionFlags);
+#[allow(non_upper_case_globals)]
+impl CFCalendarUnit {
+    pub const Era: CFCalendarUnit = CFCalendarUnit(1 << 1);
+    pub const Year: CFCalendarUnit = CFCalendarUnit(1 << 2);
+    pub const Month: CFCalendarUnit = CFCalendarUnit(1 << 3);
+    pub const Day: CFCalendarUnit = CFCalendarUnit(1 << 4);
+    pub const Hour: CFCalendarUnit = CFCalendarUnit(1 << 5);
+    pub const Minute: CFCalendarUnit = CFCalendarUnit(1 << 6);
+    pub const Second: CFCalendarUnit = CFCalendarUnit(1 << 7);
+    pub const Weekday: CFCalendarUnit = CFCalendarUnit(1 << 9);
+    pub const WeekdayOrdinal: CFCalendarUnit = CFCalendarUnit(1 << 10);
+    pub const Quarter: CFCalendarUnit = CFCalendarUnit(1 << 11);
+    pub const WeekOfMonth: CFCalendarUnit = CFCalendarUnit(1 << 12);
+    pub const WeekOfYear: CFCalendarUnit = CFCalendarUnit(1 << 13);
+    pub const YearForWeekOfYear: CFCalendarUnit = CFCalendarUnit(1 << 14);
+}
+
+/**
+A date broken into calendar components, see [CFCalendar::decompose].
+
+For the Gregorian calendar, months and days start at 1.  Seconds are whole; the fraction of a second
+is not a calendar component.
+*/
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash,Default)]
+pub struct CFCalendarComponents {
+    pub year: i32,
+    pub month: i32,
+    pub day: i32,
+    pub hour: i32,
+    pub minute: i32,
+    pub second: i32,
+}
+
+///The `componentDesc` for [CFCalendarComponents], in field order
+const COMPONENT_DESC: &[u8] = b"yMdHms\0";
+
+///Option for [CFCalendar::add_components], see cocoa docs for `kCFCalendarComponentsWrap`.
+#[allow(non_upper_case_globals)]
+const kCFCalendarComponentsWrap: CFOptionFlags = 1 << 0;
+
+cf_type!(
+    ///A calendar, which converts between absolute times and calendar components in a time zone.
+    ///
+    /// See cocoa docs for `CFCalendarRef`.
+    pub struct CFCalendar: CFCalendarGetTypeID, unique
+);
+
+extern "C" {
+    static kCFGregorianCalendar: *const CFString;
+    fn CFCalendarCopyCurrent() -> CreateRule<CFCalendar>;
+    fn CFCalendarCreateWithIdentifier(allocator: *const CFAllocator, identifier: *const CFString) -> CreateRule<CFCalendar>;
+    fn CFCalendarCopyTimeZone(calendar: *const CFCalendar) -> CreateRule<CFTimeZone>;
+    fn CFCalendarSetTimeZone(calendar: *const CFCalendar, tz: *const CFTimeZone);
+    fn CFCalendarGetRangeOfUnit(calendar: *const CFCalendar, smallerUnit: CFCalendarUnit, biggerUnit: CFCalendarUnit, at: CFAbsoluteTime) -> CFRange;
+    fn CFCalendarGetMinimumRangeOfUnit(calendar: *const CFCalendar, unit: CFCalendarUnit) -> CFRange;
+    fn CFCalendarGetMaximumRangeOfUnit(calendar: *const CFCalendar, unit: CFCalendarUnit) -> CFRange;
+    fn CFCalendarDecomposeAbsoluteTime(calendar: *const CFCalendar, at: CFAbsoluteTime, componentDesc: *const c_char, ...) -> bool;
+    fn CFCalendarComposeAbsoluteTime(calendar: *const CFCalendar, at: *mut CFAbsoluteTime, componentDesc: *const c_char, ...) -> bool;
+    fn CFCalendarAddComponents(calendar: *const CFCalendar, at: *mut CFAbsoluteTime, options: CFOptionFlags, componentDesc: *const c_char, ...) -> bool;
+}
+
+fn range_or_none(range: CFRange) -> Option<CFRange> {
+    if range.location == kCFNotFound { None } else { Some(range) }
+}
+
+impl CFCalendar {
+    ///The user's calendar, see cocoa docs for `CFCalendarCopyCurrent`.
+    ///
+    /// Calendars can be reconfigured, e.g. with [Self::set_time_zone], so they are returned as [StrongMutCell].
+    ///
+    /// # Panics
+    /// If CF fails to allocate the object, see [allocation failure](crate#allocation-failure).
+    pub fn current() -> StrongMutCell<CFCalendar> {
+        unsafe { CFCalendarCopyCurrent().into_cell().expect("CFCalendarCopyCurrent returned NULL").assuming_mut() }
+    }
+    ///A Gregorian calendar, in the default time zone.
+    ///
+    /// # Panics
+    /// If CF fails to allocate the object, see [allocation failure](crate#allocation-failure).
+    pub fn gregorian() -> StrongMutCell<CFCalendar> {
+        Self::gregorian_in(None)
+    }
+    ///Like [Self::gregorian], using `allocator`, or the default allocator if `None`.
+    pub fn gregorian_in(allocator: Option<&CFAllocator>) -> StrongMutCell<CFCalendar> {
+        let identifier = unsafe { &*kCFGregorianCalendar };
+        Self::from_identifier_in(identifier, allocator).expect("CFCalendarCreateWithIdentifier returned NULL")
+    }
+    ///Creates a calendar from an identifier like `gregorian`.  Returns `None` if the identifier is unknown.
+    ///
+    /// See cocoa docs for `CFCalendarCreateWithIdentifier`.
+    pub fn from_identifier(identifier: &CFString) -> Option<StrongMutCell<CFCalendar>> {
+        Self::from_identifier_in(identifier, None)
+    }
+    ///Like [Self::from_identifier], using `allocator`, or the default allocator if `None`.
+    pub fn from_identifier_in(identifier: &CFString, allocator: Option<&CFAllocator>) -> Option<StrongMutCell<CFCalendar>> {
+        unsafe {
+            CFCalendarCreateWithIdentifier(CFAllocator::ptr_or_default(allocator), identifier).into_cell().map(|c| c.assuming_mut())
+        }
+    }
+    ///The time zone the calendar works in.
+    ///
+    /// # Panics
+    /// If CF fails to allocate the object, see [allocation failure](crate#allocation-failure).
+    pub fn time_zone(&self) -> StrongCell<CFTimeZone> {
+        unsafe { CFCalendarCopyTimeZone(self) }.into_cell().expect("CFCalendarCopyTimeZone returned NULL")
+    }
+    ///Sets the time zone the calendar works in.
+    pub fn set_time_zone(&mut self, time_zone: &CFTimeZone) {
+        unsafe { CFCalendarSetTimeZone(self, time_zone) }
+    }
+    ///Breaks `at` into components in the calendar's time zone.  See cocoa docs for `CFCalendarDecomposeAbsoluteTime`.
+    pub fn decompose(&self, at: CFAbsoluteTime) -> Option<CFCalendarComponents> {
+        let mut c = CFCalendarComponents::default();
+        let ok = unsafe {
+            CFCalendarDecomposeAbsoluteTime(self, at, COMPONENT_DESC.as_ptr() as *const c_char,
+                &mut c.year as *mut c_int, &mut c.month as *mut c_int, &mut c.day as *mut c_int,
+                &mut c.hour as *mut c_int, &mut c.minute as *mut c_int, &mut c.second as *mut c_int)
+        };
+        if ok { Some(c) } else { None }
+    }
+    ///The absolute time of `components` in the calendar's time zone.  See cocoa docs for `CFCalendarComposeAbsoluteTime`.
+    pub fn compose(&self, components: &CFCalendarComponents) -> Option<CFAbsoluteTime> {
+        let c = components;
+        let mut at: CFAbsoluteTime = 0.0;
+        let ok = unsafe {
+            CFCalendarComposeAbsoluteTime(self, &mut at, COMPONENT_DESC.as_ptr() as *const c_char,
+                c.year, c.month, c.day, c.hour, c.minute, c.second)
+        };
+        if ok { Some(at) } else { None }
+    }
+    /**
+    Adds `amounts` to `at`, e.g. a `month` of 1 moves to the same day next month.
+
+    If `wrap` is true, overflow in a unit doesn't carry into larger units.  See cocoa docs for `CFCalendarAddComponents`.
+    */
+    pub fn add_components(&self, at: CFAbsoluteTime, amounts: &CFCalendarComponents, wrap: bool) -> Option<CFAbsoluteTime> {
+        let c = amounts;
+        let mut at = at;
+        let options = if wrap { kCFCalendarComponentsWrap } else { 0 };
+        let ok = unsafe {
+            CFCalendarAddComponents(self, &mut at, options, COMPONENT_DESC.as_ptr() as *const c_char,
+                c.year, c.month, c.day, c.hour, c.minute, c.second)
+        };
+        if ok { Some(at) } else { None }
+    }
+    ///The range `smaller` can take within the `bigger` unit containing `at`, e.g. the days in a given month.
+    ///
+    /// Returns `None` if `smaller` isn't contained in `bigger`.  See cocoa docs for `CFCalendarGetRangeOfUnit`.
+    pub fn range_of_unit(&self, smaller: CFCalendarUnit, bigger: CFCalendarUnit, at: CFAbsoluteTime) -> Option<CFRange> {
+        range_or_none(unsafe { CFCalendarGetRangeOfUnit(self, smaller, bigger, at) })
+    }
+    ///The values `unit` takes in every instance of the next larger unit, e.g. 1-28 for days.
+    pub fn minimum_range_of_unit(&self, unit: CFCalendarUnit) -> Option<CFRange> {
+        range_or_none(unsafe { CFCalendarGetMinimumRangeOfUnit(self, unit) })
+    }
+    ///The values `unit` takes in any instance of the next larger unit, e.g. 1-31 for days.
+    pub fn maximum_range_of_unit(&self, unit: CFCalendarUnit) -> Option<CFRange> {
+        range_or_none(unsafe { CFCalendarGetMaximumRangeOfUnit(self, unit) })
+    }
+}
+
+#[cfg(test)] mod tests {
+    use crate::{CFDate, CFRange, CFString, CFTimeZone};
+    use super::{CFCalendar, CFCalendarComponents, CFCalendarUnit};
+
+    #[test] fn decompose_in_zone() {
+        let mut calendar = CFCalendar::gregorian();
+        calendar.set_time_zone(&CFTimeZone::from_seconds_from_gmt(0.0).unwrap());
+        let reference = CFDate::from_absolute_time(0.0);
+        let components = calendar.decompose(reference.absolute_time()).unwrap();
+        assert_eq!(components, CFCalendarComponents { year: 2001, month: 1, day: 1, hour: 0, minute: 0, second: 0 });
+
+        let tokyo = CFTimeZone::from_name(&CFString::from_str("Asia/Tokyo")).unwrap();
+        calendar.set_time_zone(&tokyo);
+        assert_eq!(calendar.time_zone().name().as_string(), "Asia/Tokyo");
+        assert_eq!(calendar.decompose(-1.0).unwrap(), CFCalendarComponents { year: 2001, month: 1, day: 1, hour: 8, minute: 59, second: 59 });
+        assert_eq!(calendar.compose(&CFCalendarComponents { year: 2001, month: 1, day: 1, hour: 9, minute: 0, second: 0 }), Some(0.0));
+    }
+
+    #[test] fn add_and_ranges() {
+        let mut calendar = CFCalendar::gregorian();
+        calendar.set_time_zone(&CFTimeZone::from_seconds_from_gmt(0.0).unwrap());
+        let jan_31 = calendar.compose(&CFCalendarComponents { year: 2004, month: 1, day: 31, ..Default::default() }).unwrap();
+        let next_month = calendar.add_components(jan_31, &CFCalendarComponents { month: 1, ..Default::default() }, false).unwrap();
+        assert_eq!(calendar.decompose(next_month).unwrap(), CFCalendarComponents { year: 2004, month: 2, day: 29, ..Default::default() });
+        assert_eq!(calendar.range_of_unit(CFCalendarUnit::Day, CFCalendarUnit::Month, next_month), Some(CFRange { location: 1, length: 29 }));
+        assert_eq!(calendar.maximum_range_of_unit(CFCalendarUnit::Day), Some(CFRange { location: 1, length: 31 }));
+        assert_eq!(calendar.minimum_range_of_unit(CFCalendarUnit::Day), Some(CFRange { location: 1, length: 28 }));
+    }
+}
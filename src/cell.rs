@@ -1,44 +1,166 @@
-use crate::base::{CFType, CFTypeWithBaseType, CFTypeBehavior, CFTypeAny};
+use crate::base::{CFType, CFShared, CFTypeWithBaseType, CFTypeBehavior, CFTypeAny, CFMutableCopy, CFThreadSafe, CFSubtype, cf_equal, cf_hash};
+use crate::error::TypeMismatch;
 use std::ffi::c_void;
 use std::ops::{Deref, DerefMut};
 use std::fmt::{Debug, Display, Formatter};
+use std::hash::{Hash, Hasher};
 use std::mem::forget;
 use std::ptr::NonNull;
+#[cfg(debug_assertions)]
+use std::sync::atomic::{AtomicBool, Ordering};
+
+#[cfg(debug_assertions)]
+static LOG_OWNERSHIP: AtomicBool = AtomicBool::new(false);
+
+/**
+Logs every ownership change made by [StrongCell] and [StrongMutCell] to stderr.
+
+Each line has the event, the type, the address and the retain count.  An object is owned when it's
+`adopt`ed from a create function or `retain`ed, and gives up ownership on `release`.  An over-release shows up
+as more releases than adopts and retains for one address.
+
+Logging only happens in debug builds.  In release builds this does nothing, so callers don't need their own `cfg`.
+*/
+pub fn set_ownership_logging(enabled: bool) {
+    #[cfg(debug_assertions)]
+    LOG_OWNERSHIP.store(enabled, Ordering::Relaxed);
+    #[cfg(not(debug_assertions))]
+    let _ = enabled;
+}
+
+fn log_ownership<T: CFType>(event: &str, ptr: NonNull<T>) {
+    #[cfg(debug_assertions)]
+    if LOG_OWNERSHIP.load(Ordering::Relaxed) {
+        let count = unsafe{ &*ptr.as_ptr() }.retain_count();
+        eprintln!("{} {} {:?} retain count {}", event, std::any::type_name::<T>(), ptr, count);
+    }
+    #[cfg(not(debug_assertions))]
+    let _ = (event, ptr);
+}
 
 ///A 'smart pointer' that keeps a strong reference to the CF object.
 ///
 /// The object will be released when the `StrongCell` is dropped.
 ///
 /// For a mutable version see [StrongMutCell].
+///
+/// `StrongCell<T>` is `Send` and `Sync` when `T` is [CFThreadSafe]:
+/// ```
+/// fn assert_send_sync<T: Send + Sync>() {}
+/// assert_send_sync::<core_foundationr::StrongCell<core_foundationr::CFString>>();
+/// ```
+/// Other types stay on their thread:
+/// ```compile_fail
+/// fn assert_send<T: Send>() {}
+/// assert_send::<core_foundationr::StrongCell<core_foundationr::CFStringTokenizer>>();
+/// ```
 pub struct StrongCell<T: CFType>(NonNull<T>);
+unsafe impl<T: CFThreadSafe> Send for StrongCell<T> {}
+unsafe impl<T: CFThreadSafe> Sync for StrongCell<T> {}
 impl<T: CFType> StrongCell<T> {
     ///Creates a [StrongCell], assuming the pointer is retained already (so the conversion is a no-op) and non-null.
     ///
-    ///This is unsafe, because there's no way to check if it's retained or even valid
+    ///This is unsafe, because there's no way to check if it's retained or even valid.
+    ///
+    /// See [Self::from_create] for a version that checks for null.
     pub unsafe fn assuming_retained_nonnull(t: *const T) -> Self {
-        Self(NonNull::new_unchecked(t as *mut T))
+        let ptr = NonNull::new_unchecked(t as *mut T);
+        log_ownership("adopt", ptr);
+        Self(ptr)
+    }
+    /**
+    Takes ownership of a pointer returned from a function following the CF "Create rule", that is, a function
+    with `Create` or `Copy` in the name.  Such pointers are already retained, so this is a no-op.
+
+    Returns `None` if the pointer is null.
+
+    # Safety
+    The pointer must be null, or point to a valid object of type `T` with a retain that the caller owns.
+    */
+    pub unsafe fn from_create(t: *const T) -> Option<Self> {
+        let ptr = NonNull::new(t as *mut T)?;
+        log_ownership("adopt", ptr);
+        Some(StrongCell(ptr))
+    }
+    /**
+    Retains a pointer returned from a function following the CF "Get rule", that is, a function that
+    returns an object the caller doesn't own.
+
+    Returns `None` if the pointer is null.
+
+    # Safety
+    The pointer must be null, or point to a valid object of type `T`.
+    */
+    pub unsafe fn from_get(t: *const T) -> Option<Self> {
+        let ptr = NonNull::new(t as *mut T)?;
+        CFRetain(ptr.as_ptr() as *const c_void);
+        log_ownership("retain", ptr);
+        Some(StrongCell(ptr))
     }
     ///Perform a checked cast into the given type.
     ///
     /// This transfers ownership to the new type.
+    ///
+    /// # Panics
+    /// If the object has a different type.  See [Self::try_cast] for a version that doesn't panic.
     pub fn cast_checked<R: CFTypeWithBaseType>(self) -> StrongCell<R> {
+        self.try_cast().unwrap_or_else(|(_, e)| panic!("{}", e))
+    }
+    ///Perform a checked cast into the given type.
+    ///
+    /// On success, this transfers ownership to the new type.  On failure, the original cell is returned
+    /// along with the error, so ownership isn't lost.
+    pub fn try_cast<R: CFTypeWithBaseType>(self) -> Result<StrongCell<R>, (StrongCell<T>, TypeMismatch)> {
         let any = unsafe{ CFTypeAny::from_ptr(self.0.as_ptr() as *const c_void) };
-        assert_eq!(CFTypeBehavior::type_id(unsafe{ &*any}), R::type_id());
+        let actual = CFTypeBehavior::type_id(unsafe{ &*any});
+        let expected = R::type_id();
+        if actual != expected {
+            return Err((self, TypeMismatch { expected, actual }));
+        }
         //should be safe since both source and dst have the static lifetime (e.g. StrongCell)
         let new_type = unsafe{ R::from_ptr(self.as_ptr()) };
-        let new_cell = unsafe{ StrongCell::assuming_retained_nonnull(new_type)};
+        //ownership moves, rather than being adopted
+        let new_cell = StrongCell(unsafe{ NonNull::new_unchecked(new_type as *mut R) });
         std::mem::forget(self);
-        new_cell
+        Ok(new_cell)
+    }
+    ///Converts to a supertype, like `StrongCell<CFMutableString>` to `StrongCell<CFString>`.
+    ///
+    /// This transfers ownership, and never fails.  To go the other way, see [Self::try_cast].
+    pub fn upcast<S: CFType>(self) -> StrongCell<S> where T: CFSubtype<S> {
+        let ptr = self.0.cast::<S>();
+        forget(self);
+        StrongCell(ptr)
+    }
+    ///Converts to [CFTypeAny], which every CF type is a subtype of.
+    pub fn into_any(self) -> StrongCell<CFTypeAny> {
+        let ptr = self.0.cast::<CFTypeAny>();
+        forget(self);
+        StrongCell(ptr)
+    }
+    ///Whether both cells point to the same object.
+    ///
+    /// Compare with `==`, which uses `CFEqual` to compare the values.
+    pub fn ptr_eq(this: &Self, other: &Self) -> bool {
+        this.0 == other.0
+    }
+    ///Retains the object, creating a new strong reference to it.
+    ///
+    /// Only [CFShared] objects can be retained, since objects owned by a [StrongMutCell] may be mutated.
+    pub fn retain(t: &T) -> Self where T: CFShared {
+        unsafe{ StrongCell::retain_assuming_nonnull(t) }
     }
     /**
     Retains the passed pointer.
 
     # Safety
-    Assumes the pointer is non-null.
+    Assumes the pointer is non-null.  See [Self::from_get] for a version that checks for null.
     */
     pub unsafe fn retain_assuming_nonnull(t: *const T) -> Self {
         CFRetain(t as *const c_void);
-        Self(NonNull::new_unchecked(t as *mut T))
+        let ptr = NonNull::new_unchecked(t as *mut T);
+        log_ownership("retain", ptr);
+        Self(ptr)
     }
     /**
     Casts to mutable type.
@@ -53,9 +175,29 @@ impl<T: CFType> StrongCell<T> {
         s
     }
 }
+///Retains the object.  Both cells refer to the same object.
+impl<T: CFType> Clone for StrongCell<T> {
+    fn clone(&self) -> Self {
+        //a StrongCell is never mutably owned
+        unsafe{ StrongCell::retain_assuming_nonnull(&**self) }
+    }
+}
+///Compares the objects with `CFEqual`.  For identity, see [StrongCell::ptr_eq].
+impl<T: CFType> PartialEq for StrongCell<T> {
+    fn eq(&self, other: &Self) -> bool {
+        cf_equal::<T, T>(self, other)
+    }
+}
+impl<T: CFType> Eq for StrongCell<T> {}
+///Hashes the object with `CFHash`, consistent with `CFEqual`.
+impl<T: CFType> Hash for StrongCell<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        cf_hash::<T>(self).hash(state)
+    }
+}
 impl<T: CFType> std::fmt::Debug for StrongCell<T> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        f.write_fmt(format_args!("StrongCell<{}>({:?})",stringify!(T),self.0))
+        f.write_fmt(format_args!("StrongCell<{}>({:?})",std::any::type_name::<T>(),self.0))
     }
 }
 impl <T: std::fmt::Display + CFType> std::fmt::Display for StrongCell<T> {
@@ -67,13 +209,13 @@ impl <T: std::fmt::Display + CFType> std::fmt::Display for StrongCell<T> {
 }
 
 extern "C" {
-    fn CFRelease(type_ref: *const c_void);
     fn CFRetain(type_ref: *const c_void);
 }
 
 impl<T: CFType> Drop for StrongCell<T> {
     fn drop(&mut self) {
-        unsafe{ CFRelease(self.0.as_ptr() as *const c_void) };
+        log_ownership("release", self.0);
+        unsafe{ crate::weak::release(self.0.as_ptr() as *const c_void) };
     }
 }
 impl<T: CFType> Deref for StrongCell<T> {
@@ -83,17 +225,60 @@ impl<T: CFType> Deref for StrongCell<T> {
         unsafe{ &*self.0.as_ptr() }
     }
 }
+///Lets a cell be passed where `impl AsRef<CFTypeAny>` is expected.
+impl<T: CFType + AsRef<CFTypeAny>> AsRef<CFTypeAny> for StrongCell<T> {
+    fn as_ref(&self) -> &CFTypeAny {
+        (**self).as_ref()
+    }
+}
 
 /**
 Like [StrongCell], but mutable.
 
 To create one, see [StrongCell::assuming_mut].
+
+`StrongMutCell<T>` is `Send` when `T` is, so it can be moved to another thread:
+```
+fn assert_send<T: Send>() {}
+assert_send::<core_foundationr::StrongMutCell<core_foundationr::CFMutableData>>();
+```
+However it is never `Sync`, since the object isn't safe to read while another thread mutates it:
+```compile_fail
+fn assert_sync<T: Sync>() {}
+assert_sync::<core_foundationr::StrongMutCell<core_foundationr::CFMutableData>>();
+```
 */
 pub struct StrongMutCell<T: CFType>(NonNull<T>);
+unsafe impl<T: CFType + Send> Send for StrongMutCell<T> {}
+
+impl<T: CFType> StrongMutCell<T> {
+    ///Converts to a supertype, like `StrongMutCell<CFMutableString>` to `StrongCell<CFString>`.
+    ///
+    /// This gives up exclusive access, so the result is a shared [StrongCell].
+    pub fn upcast<S: CFType>(self) -> StrongCell<S> where T: CFSubtype<S> {
+        let ptr = self.0.cast::<S>();
+        forget(self);
+        StrongCell(ptr)
+    }
+}
+impl<T: CFMutableCopy<Mutable=T>> StrongMutCell<T> {
+    ///Copies into a new mutable object, see [CFMutableCopy].
+    ///
+    /// Since a [StrongMutCell] has exclusive access, it can't be shared with a retain.
+    pub fn copy(&self) -> StrongMutCell<T> {
+        self.mutable_copy()
+    }
+}
+///Creates a new object with [StrongMutCell::copy].
+impl<T: CFMutableCopy<Mutable=T>> Clone for StrongMutCell<T> {
+    fn clone(&self) -> Self {
+        self.copy()
+    }
+}
 
 impl<T: CFType> Debug for StrongMutCell<T> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        f.write_fmt(format_args!("StrongMutCell<{}>({:?})",stringify!(CFError),self.0))
+        f.write_fmt(format_args!("StrongMutCell<{}>({:?})",std::any::type_name::<T>(),self.0))
     }
 }
 impl<T: CFType + Display> Display for StrongMutCell<T> {
@@ -106,7 +291,8 @@ impl<T: CFType + Display> Display for StrongMutCell<T> {
 
 impl<T: CFType> Drop for StrongMutCell<T> {
     fn drop(&mut self) {
-        unsafe{ CFRelease(self.0.as_ptr() as *const c_void) };
+        log_ownership("release", self.0);
+        unsafe{ crate::weak::release(self.0.as_ptr() as *const c_void) };
     }
 }
 
@@ -121,4 +307,73 @@ impl<T: CFType> DerefMut for StrongMutCell<T> {
     fn deref_mut(&mut self) -> &mut Self::Target {
         unsafe{ &mut *self.0.as_ptr() }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)] mod tests {
+    use crate::{CFString, CFMutableData, StrongCell, CFTypeBehavior};
+
+    #[test] fn from_null() {
+        let create = unsafe{ StrongCell::<CFString>::from_create(std::ptr::null()) };
+        assert!(create.is_none());
+        let get = unsafe{ StrongCell::<CFString>::from_get(std::ptr::null()) };
+        assert!(get.is_none());
+
+        let string = CFString::from_str("hello");
+        let get = unsafe{ StrongCell::from_get(&*string as *const CFString) }.unwrap();
+        assert!(StrongCell::ptr_eq(&get, &string));
+    }
+    #[test] fn retain_count() {
+        let data = crate::CFData::copy_slice(b"retain count");
+        assert_eq!(data.retain_count(), 1);
+        super::set_ownership_logging(true);
+        let clone = data.clone();
+        assert_eq!(data.retain_count(), 2);
+        drop(clone);
+        super::set_ownership_logging(false);
+        assert_eq!(data.retain_count(), 1);
+        assert!(format!("{:?}", data).starts_with("StrongCell<core_foundationr::data::CFData>"));
+    }
+    #[test] fn clone_retains() {
+        let string = CFString::from_str("hello");
+        let clone = string.clone();
+        assert_eq!(string.as_ptr(), clone.as_ptr());
+        let retained = StrongCell::retain(&*string);
+        drop(string);
+        drop(clone);
+        assert_eq!(retained.as_string(), "hello");
+    }
+
+    #[test] fn eq_hash() {
+        use std::collections::HashMap;
+        let a = CFString::from_str("hello");
+        let b = CFString::from_str("hello");
+        assert!(a == b);
+        assert!(*a == *b);
+        assert!(StrongCell::ptr_eq(&a, &a.clone()));
+        assert!(a != CFString::from_str("world"));
+        let mut map = HashMap::new();
+        map.insert(a, 1);
+        assert_eq!(map.get(&b), Some(&1));
+    }
+
+    #[test] fn send_across_threads() {
+        let string = CFString::from_str("hello");
+        let clone = string.clone();
+        let joined = std::thread::spawn(move || clone.as_string()).join().unwrap();
+        assert_eq!(joined, "hello");
+        let mut data = CFMutableData::new();
+        data.append(b"hello");
+        let data = std::thread::spawn(move || { data.append(b" world"); data }).join().unwrap();
+        assert_eq!(data.as_slice(), b"hello world");
+    }
+
+    #[test] fn copy_mutable() {
+        let mut data = CFMutableData::new();
+        data.append(b"hello");
+        let mut copy = data.copy();
+        assert_ne!(data.as_ptr(), copy.as_ptr());
+        copy.append(b" world");
+        assert_eq!(data.as_slice(), b"hello");
+        assert_eq!(copy.as_slice(), b"hello world");
+    }
+}
@@ -0,0 +1,78 @@
+//! Conversions between [CFDate] and [::chrono::DateTime], and between [CFTimeZone] and [::chrono_tz::Tz], behind the `chrono` feature.
+
+use ::chrono::{DateTime, TimeZone, Utc};
+use ::chrono_tz::Tz;
+use crate::cell::StrongCell;
+use crate::error::{DateOutOfRange, UnknownTimeZone};
+use crate::{CFDate, CFString, CFTimeZone};
+
+///Converts to the nearest [CFDate].  CF stores dates as `f64` seconds, so this is precise to about a microsecond for present-day dates.
+impl<Z: TimeZone> From<DateTime<Z>> for StrongCell<CFDate> {
+    fn from(date: DateTime<Z>) -> Self {
+        CFDate::from_unix_timestamp(date.timestamp(), date.timestamp_subsec_nanos())
+    }
+}
+
+///Converts to the nearest nanosecond.
+impl TryFrom<&CFDate> for DateTime<Utc> {
+    type Error = DateOutOfRange;
+
+    fn try_from(date: &CFDate) -> Result<Self, Self::Error> {
+        let out_of_range = DateOutOfRange { absolute_time: date.absolute_time() };
+        let (seconds, nanos) = date.unix_timestamp().ok_or(out_of_range)?;
+        DateTime::from_timestamp(seconds, nanos).ok_or(out_of_range)
+    }
+}
+
+///Looks up the zone with the same name, like `Europe/Berlin`.
+impl TryFrom<&CFTimeZone> for Tz {
+    type Error = UnknownTimeZone;
+
+    fn try_from(zone: &CFTimeZone) -> Result<Self, Self::Error> {
+        let name = zone.name().as_string();
+        name.parse().map_err(|_| UnknownTimeZone { name })
+    }
+}
+
+///Looks up the zone with the same name, like `Europe/Berlin`.
+impl TryFrom<Tz> for StrongCell<CFTimeZone> {
+    type Error = UnknownTimeZone;
+
+    fn try_from(zone: Tz) -> Result<Self, Self::Error> {
+        CFTimeZone::from_name(&CFString::from_str(zone.name())).ok_or_else(|| UnknownTimeZone { name: zone.name().to_owned() })
+    }
+}
+
+#[cfg(test)] mod tests {
+    use ::chrono::{DateTime, TimeZone, Utc};
+    use ::chrono_tz::Tz;
+    use crate::{CFDate, CFTimeZone, StrongCell};
+
+    #[test] fn date_round_trip() {
+        let reference = Utc.with_ymd_and_hms(2001, 1, 1, 0, 0, 0).unwrap() + ::chrono::Duration::milliseconds(500);
+        let date: StrongCell<CFDate> = reference.into();
+        assert_eq!(date.absolute_time(), 0.5);
+        assert_eq!(DateTime::<Utc>::try_from(&*date).unwrap(), reference);
+
+        //present-day dates keep microseconds through CF
+        let summer = Utc.with_ymd_and_hms(2021, 7, 1, 12, 30, 15).unwrap() + ::chrono::Duration::microseconds(123_456);
+        let date: StrongCell<CFDate> = summer.with_timezone(&::chrono_tz::Europe::Berlin).into();
+        let back = DateTime::<Utc>::try_from(&*date).unwrap();
+        assert!((back - summer).num_nanoseconds().unwrap().abs() < 1_000);
+        //and the nearest nanosecond converts back to the same CFDate
+        let again: StrongCell<CFDate> = back.into();
+        assert_eq!(again.absolute_time(), date.absolute_time());
+
+        let before_reference = CFDate::from_absolute_time(-0.25);
+        assert_eq!(DateTime::<Utc>::try_from(&*before_reference).unwrap(), reference - ::chrono::Duration::milliseconds(750));
+        assert!(DateTime::<Utc>::try_from(&*CFDate::from_absolute_time(f64::NAN)).is_err());
+    }
+
+    #[test] fn time_zone() {
+        let berlin: StrongCell<CFTimeZone> = Tz::Europe__Berlin.try_into().unwrap();
+        assert_eq!(berlin.name().as_string(), "Europe/Berlin");
+        assert_eq!(Tz::try_from(&*berlin).unwrap(), Tz::Europe__Berlin);
+        let fixed = CFTimeZone::from_seconds_from_gmt(90.0 * 60.0).unwrap();
+        assert!(Tz::try_from(&*fixed).is_err());
+    }
+}
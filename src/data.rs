@@ -1,27 +1,139 @@
-use crate::base::{CFType, CFAllocator, CFIndex, OpaqueCType};
-use crate::cell::StrongCell;
+use std::borrow::Borrow;
+use std::hash::{Hash, Hasher};
+use std::io::{Cursor, Write};
+use std::ops::{BitOr, Deref};
+use crate::base::{CFType, CFShared, CFSubtype, CFTypeID, CFTypeWithBaseType, CFMutableCopy, CFTypeAny, CFAllocator, CFIndex, CFRange, CFOptionFlags, OpaqueCType, kCFNotFound};
+use crate::base::{cf_eq_hash, cf_thread_safe, cf_subtype};
+use crate::cell::{StrongCell, StrongMutCell};
+use crate::ownership::CreateRule;
 
 extern "C" {
-    fn CFDataCreate(allocator: *const CFAllocator, bytes: *const u8, length: CFIndex) -> *const CFData;
+    fn CFDataGetTypeID() -> CFTypeID;
+    fn CFDataCreate(allocator: *const CFAllocator, bytes: *const u8, length: CFIndex) -> CreateRule<CFData>;
+    fn CFDataCreateWithBytesNoCopy(allocator: *const CFAllocator, bytes: *const u8, length: CFIndex, bytesDeallocator: *const CFAllocator) -> CreateRule<CFData>;
     fn CFDataGetLength(theData: *const CFData) -> CFIndex;
     fn CFDataGetBytePtr(theData: *const CFData) -> *const u8;
+    fn CFDataFind(theData: *const CFData, dataToFind: *const CFData, searchRange: CFRange, compareOptions: CFDataSearchFlags) -> CFRange;
+    fn CFDataCreateMutable(allocator: *const CFAllocator, capacity: CFIndex) -> CreateRule<CFMutableData>;
+    fn CFDataCreateMutableCopy(allocator: *const CFAllocator, capacity: CFIndex, theData: *const CFData) -> CreateRule<CFMutableData>;
+    fn CFDataGetMutableBytePtr(theData: *const CFMutableData) -> *mut u8;
+    fn CFDataAppendBytes(theData: *const CFMutableData, bytes: *const u8, length: CFIndex);
+    fn CFDataReplaceBytes(theData: *const CFMutableData, range: CFRange, newBytes: *const u8, newLength: CFIndex);
+    fn CFDataDeleteBytes(theData: *const CFMutableData, range: CFRange);
+    fn CFDataSetLength(theData: *const CFMutableData, length: CFIndex);
+    fn CFDataIncreaseLength(theData: *const CFMutableData, extraLength: CFIndex);
 }
 
+///Options for [CFData::find].  Combine with `|`.
+#[repr(transparent)]
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub struct CFDataSearchFlags(CFOptionFlags);
+#[allow(non_upper_case_globals)]
+impl CFDataSearchFlags {
+    pub const None: CFDataSearchFlags = CFDataSearchFlags(0);
+    ///Search from the end of the range
+    pub const Backwards: CFDataSearchFlags = CFDataSearchFlags(1 << 0);
+    ///Only match at the start of the range (or the end, if searching backwards)
+    pub const Anchored: CFDataSearchFlags = CFDataSearchFlags(1 << 1);
+}
+impl BitOr for CFDataSearchFlags {
+    type Output = CFDataSearchFlags;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        CFDataSearchFlags(self.0 | rhs.0)
+    }
+}
+
+///Immutable bytes.
+///
+/// Derefs to `[u8]`, so slice methods like `len()` are available directly.
 #[repr(C)]
 pub struct CFData(OpaqueCType);
 impl CFType for CFData {}
+unsafe impl CFShared for CFData {}
+cf_thread_safe!(CFData);
+cf_subtype!(CFData => CFTypeAny);
+impl CFTypeWithBaseType for CFData {
+    fn type_id() -> CFTypeID {
+        unsafe { CFDataGetTypeID() }
+    }
+}
 #[allow(non_snake_case)]
 impl CFData {
     ///- note: objc knows a faster path for owned strings
     ///- note: uncertain about faster path for static strings?
+    ///
+    /// # Panics
+    /// If CF fails to allocate the object, see [allocation failure](crate#allocation-failure).
     pub fn from_str(str: &str) -> StrongCell<CFData> {
-        let raw = unsafe{ CFDataCreate(CFAllocator::null(), str.as_ptr(), str.as_bytes().len() as CFIndex) };
-        unsafe{ StrongCell::assuming_retained_nonnull(raw) }
+        Self::from_str_in(str, None)
+    }
+    ///Like [Self::from_str], using `allocator`, or the default allocator if `None`.
+    pub fn from_str_in(str: &str, allocator: Option<&CFAllocator>) -> StrongCell<CFData> {
+        let raw = unsafe{ CFDataCreate(CFAllocator::ptr_or_default(allocator), str.as_ptr(), str.as_bytes().len() as CFIndex) };
+        raw.into_cell().expect("CFDataCreate returned NULL")
     }
     ///Copies the provided slice into the CFData.
+    ///
+    /// # Panics
+    /// If CF fails to allocate the object, see [allocation failure](crate#allocation-failure).
     pub fn copy_slice(slice: &[u8]) -> StrongCell<CFData> {
-        let raw = unsafe { CFDataCreate(CFAllocator::null(), slice.as_ptr(), slice.len().try_into().unwrap()) };
-        unsafe { StrongCell::assuming_retained_nonnull(raw) }
+        Self::copy_slice_in(slice, None)
+    }
+    ///Like [Self::copy_slice], using `allocator`, or the default allocator if `None`.
+    pub fn copy_slice_in(slice: &[u8], allocator: Option<&CFAllocator>) -> StrongCell<CFData> {
+        let raw = unsafe { CFDataCreate(CFAllocator::ptr_or_default(allocator), slice.as_ptr(), slice.len().try_into().unwrap()) };
+        raw.into_cell().expect("CFDataCreate returned NULL")
+    }
+    ///Wraps the vector's buffer without copying.  The vector is dropped when the CFData is released.
+    ///
+    /// # Panics
+    /// If CF fails to allocate the object, see [allocation failure](crate#allocation-failure).
+    pub fn from_vec(vec: Vec<u8>) -> StrongCell<CFData> {
+        CFData::from_owner(vec, None)
+    }
+    ///Like [Self::from_vec], using `allocator` for the CFData object, or the default allocator if `None`.
+    pub fn from_vec_in(vec: Vec<u8>, allocator: Option<&CFAllocator>) -> StrongCell<CFData> {
+        CFData::from_owner(vec, allocator)
+    }
+    ///Wraps the boxed buffer without copying.  The box is dropped when the CFData is released.
+    ///
+    /// # Panics
+    /// If CF fails to allocate the object, see [allocation failure](crate#allocation-failure).
+    pub fn from_boxed(boxed: Box<[u8]>) -> StrongCell<CFData> {
+        CFData::from_owner(boxed, None)
+    }
+    ///Like [Self::from_boxed], using `allocator` for the CFData object, or the default allocator if `None`.
+    pub fn from_boxed_in(boxed: Box<[u8]>, allocator: Option<&CFAllocator>) -> StrongCell<CFData> {
+        CFData::from_owner(boxed, allocator)
+    }
+    ///Wraps the static buffer without copying.
+    ///
+    /// # Panics
+    /// If CF fails to allocate the object, see [allocation failure](crate#allocation-failure).
+    pub fn from_static(slice: &'static [u8]) -> StrongCell<CFData> {
+        Self::from_static_in(slice, None)
+    }
+    ///Like [Self::from_static], using `allocator`, or the default allocator if `None`.
+    pub fn from_static_in(slice: &'static [u8], allocator: Option<&CFAllocator>) -> StrongCell<CFData> {
+        unsafe {
+            let raw = CFDataCreateWithBytesNoCopy(CFAllocator::ptr_or_default(allocator), slice.as_ptr(), slice.len().try_into().unwrap(), CFAllocator::allocator_null());
+            raw.into_cell().expect("CFDataCreateWithBytesNoCopy returned NULL")
+        }
+    }
+    ///Wraps the bytes of `owner` without copying.  `owner` is dropped exactly once, when CF releases the data.
+    ///
+    /// `allocator` is used for the CFData object itself.
+    pub(crate) fn from_owner<O: AsRef<[u8]> + Send + 'static>(owner: O, allocator: Option<&CFAllocator>) -> StrongCell<CFData> {
+        //box so the bytes don't move, even if O stores them inline
+        let owner = Box::new(owner);
+        let slice = (*owner).as_ref();
+        let (bytes, length) = (slice.as_ptr(), slice.len());
+        let deallocator = CFAllocator::dropping(owner);
+        unsafe {
+            let raw = CFDataCreateWithBytesNoCopy(CFAllocator::ptr_or_default(allocator), bytes, length.try_into().unwrap(), &*deallocator);
+            raw.into_cell().expect("CFDataCreateWithBytesNoCopy returned NULL")
+        }
     }
     pub fn GetLength(&self) -> CFIndex {
         unsafe { CFDataGetLength(self) }
@@ -33,10 +145,192 @@ impl CFData {
     }
     pub fn as_slice(&self) -> &[u8] {
         let length = self.GetLength();
+        //the byte pointer may be null when empty
+        if length == 0 {
+            return &[];
+        }
         unsafe {
             std::slice::from_raw_parts(self.GetBytePtr(), length as usize)
         }
     }
+    ///Returns a cursor implementing [std::io::Read] and [std::io::Seek] over the bytes.
+    pub fn cursor(&self) -> Cursor<&[u8]> {
+        Cursor::new(self.as_slice())
+    }
+    ///Finds `pattern` within `range`, see cocoa docs for `CFDataFind`.
+    ///
+    /// # Panics
+    /// If `range` is out of bounds.
+    pub fn find(&self, pattern: &CFData, range: CFRange, options: CFDataSearchFlags) -> Option<CFRange> {
+        range.assert_in_bounds(self.GetLength());
+        let found = unsafe{ CFDataFind(self, pattern, range, options) };
+        if found.location == kCFNotFound {
+            None
+        }
+        else {
+            Some(found)
+        }
+    }
+}
+
+impl Deref for CFData {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        self.as_slice()
+    }
+}
+impl AsRef<[u8]> for CFData {
+    fn as_ref(&self) -> &[u8] {
+        self.as_slice()
+    }
+}
+impl Borrow<[u8]> for CFData {
+    fn borrow(&self) -> &[u8] {
+        self.as_slice()
+    }
+}
+impl PartialEq for CFData {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_slice() == other.as_slice()
+    }
+}
+impl Eq for CFData {}
+impl PartialEq<[u8]> for CFData {
+    fn eq(&self, other: &[u8]) -> bool {
+        self.as_slice() == other
+    }
+}
+//consistent with the Borrow impl
+impl Hash for CFData {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_slice().hash(state)
+    }
+}
+
+///A growable [CFData].
+///
+/// Mutating methods take `&mut self`, so these are usually held in a [StrongMutCell].
+/// Derefs to `[u8]`, borrowing the cell, so the bytes can't be read after a mutation.
+#[repr(C)]
+pub struct CFMutableData(OpaqueCType);
+impl CFType for CFMutableData {}
+cf_eq_hash!(CFMutableData);
+unsafe impl CFSubtype<CFData> for CFMutableData {}
+unsafe impl CFSubtype<CFTypeAny> for CFMutableData {}
+//may be moved between threads, but mutation isn't synchronized
+unsafe impl Send for CFMutableData {}
+impl Deref for CFMutableData {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        self.as_slice()
+    }
+}
+impl AsRef<[u8]> for CFMutableData {
+    fn as_ref(&self) -> &[u8] {
+        self.as_slice()
+    }
+}
+impl CFMutableData {
+    ///Creates an empty mutable data, see cocoa docs for `CFDataCreateMutable`.
+    ///
+    /// # Panics
+    /// If CF fails to allocate the object, see [allocation failure](crate#allocation-failure).
+    pub fn new() -> StrongMutCell<CFMutableData> {
+        Self::new_in(None)
+    }
+    ///Like [Self::new], using `allocator`, or the default allocator if `None`.
+    pub fn new_in(allocator: Option<&CFAllocator>) -> StrongMutCell<CFMutableData> {
+        unsafe {
+            let raw = CFDataCreateMutable(CFAllocator::ptr_or_default(allocator), 0);
+            raw.into_cell().expect("CFDataCreateMutable returned NULL").assuming_mut()
+        }
+    }
+    ///Creates a mutable copy of `data`, see cocoa docs for `CFDataCreateMutableCopy`.
+    ///
+    /// # Panics
+    /// If CF fails to allocate the object, see [allocation failure](crate#allocation-failure).
+    pub fn copy_data(data: &CFData) -> StrongMutCell<CFMutableData> {
+        Self::copy_data_in(data, None)
+    }
+    ///Like [Self::copy_data], using `allocator`, or the default allocator if `None`.
+    pub fn copy_data_in(data: &CFData, allocator: Option<&CFAllocator>) -> StrongMutCell<CFMutableData> {
+        unsafe {
+            let raw = CFDataCreateMutableCopy(CFAllocator::ptr_or_default(allocator), 0, data);
+            raw.into_cell().expect("CFDataCreateMutableCopy returned NULL").assuming_mut()
+        }
+    }
+    //the result must not escape the borrow, see CFShared
+    fn as_immutable(&self) -> &CFData {
+        unsafe{ &*(self as *const CFMutableData as *const CFData) }
+    }
+    ///The bytes.  The slice borrows the data, so it can't outlive a mutation.
+    pub fn as_slice(&self) -> &[u8] {
+        self.as_immutable().as_slice()
+    }
+    ///Appends `bytes` to the end.
+    pub fn append(&mut self, bytes: &[u8]) {
+        unsafe { CFDataAppendBytes(self, bytes.as_ptr(), bytes.len().try_into().unwrap()) }
+    }
+    ///Replaces the bytes in `range` with `bytes`, which may be a different length.
+    ///
+    /// # Panics
+    /// If `range` is out of bounds.
+    pub fn replace_bytes(&mut self, range: CFRange, bytes: &[u8]) {
+        range.assert_in_bounds(self.as_immutable().GetLength());
+        unsafe { CFDataReplaceBytes(self, range, bytes.as_ptr(), bytes.len().try_into().unwrap()) }
+    }
+    ///Deletes the bytes in `range`.
+    ///
+    /// # Panics
+    /// If `range` is out of bounds.
+    pub fn delete_bytes(&mut self, range: CFRange) {
+        range.assert_in_bounds(self.as_immutable().GetLength());
+        unsafe { CFDataDeleteBytes(self, range) }
+    }
+    ///Truncates or extends the data.  Extended bytes are zero.
+    pub fn set_length(&mut self, length: CFIndex) {
+        assert!(length >= 0);
+        unsafe { CFDataSetLength(self, length) }
+    }
+    ///Extends the data by `extra_length` zero bytes.
+    pub fn increase_length(&mut self, extra_length: CFIndex) {
+        assert!(extra_length >= 0);
+        unsafe { CFDataIncreaseLength(self, extra_length) }
+    }
+    ///Mutable access to the bytes.
+    pub fn as_mut_slice(&mut self) -> &mut [u8] {
+        let length = self.as_immutable().GetLength();
+        if length == 0 {
+            return &mut [];
+        }
+        unsafe {
+            std::slice::from_raw_parts_mut(CFDataGetMutableBytePtr(self), length as usize)
+        }
+    }
+}
+impl CFMutableCopy for CFData {
+    type Mutable = CFMutableData;
+    fn mutable_copy_in(&self, allocator: Option<&CFAllocator>) -> StrongMutCell<CFMutableData> {
+        CFMutableData::copy_data_in(self, allocator)
+    }
+}
+impl CFMutableCopy for CFMutableData {
+    type Mutable = CFMutableData;
+    fn mutable_copy_in(&self, allocator: Option<&CFAllocator>) -> StrongMutCell<CFMutableData> {
+        CFMutableData::copy_data_in(self.as_immutable(), allocator)
+    }
+}
+impl Write for CFMutableData {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.append(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
 }
 
 #[test] fn from_str() {
@@ -51,4 +345,99 @@ impl CFData {
     let result = CFData::copy_slice(b"hello world");
     let str = result.description().as_string();
     assert!(str.starts_with("<CFData"))
-}
\ No newline at end of file
+}
+
+#[test] #[allow(clippy::octal_escapes)] fn mutable() {
+    let mut data = CFMutableData::new();
+    data.append(b"hello world");
+    data.replace_bytes(CFRange { location: 0, length: 5 }, b"goodbye");
+    assert_eq!(data.as_slice(), b"goodbye world");
+    data.delete_bytes(CFRange { location: 7, length: 6 });
+    assert_eq!(data.as_slice(), b"goodbye");
+    data.set_length(4);
+    data.increase_length(2);
+    assert_eq!(data.as_slice(), b"good\0\0");
+    data.as_mut_slice()[4] = b'!';
+    write!(data, "{}", 42).unwrap();
+    assert_eq!(data.as_slice(), b"good!\042");
+
+    let copy = CFMutableData::copy_data(&CFData::copy_slice(&data));
+    assert_eq!(copy.as_slice(), data.as_slice());
+}
+
+#[test] fn mutable_borrow() {
+    //a slice borrows the cell, so it can't be read after the data reallocates
+    let mut data = CFMutableData::new();
+    data.append(b"x");
+    let slice = &data[..];
+    assert_eq!(slice, b"x");
+    data.append(&[0; 1 << 20]);
+    assert_eq!(data.len(), 1 + (1 << 20));
+    //sharing the object gives up mutation
+    let shared: StrongCell<CFData> = data.upcast();
+    let array = crate::CFArray::from_slice(&[&*shared]);
+    assert_eq!(array.get(0).unwrap()[0], b'x');
+}
+
+#[test] fn no_copy() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    static DROPS: AtomicUsize = AtomicUsize::new(0);
+    struct Owner(Vec<u8>);
+    impl AsRef<[u8]> for Owner {
+        fn as_ref(&self) -> &[u8] { &self.0 }
+    }
+    impl Drop for Owner {
+        fn drop(&mut self) { DROPS.fetch_add(1, Ordering::SeqCst); }
+    }
+    let vec = vec![7; 4096];
+    let ptr = vec.as_ptr();
+    let data = CFData::from_owner(Owner(vec), None);
+    assert_eq!(data.GetBytePtr(), ptr);
+    assert_eq!(data.as_slice(), &[7; 4096]);
+    assert_eq!(DROPS.load(Ordering::SeqCst), 0);
+    drop(data);
+    assert_eq!(DROPS.load(Ordering::SeqCst), 1);
+
+    let vec = vec![1,2,3];
+    let ptr = vec.as_ptr();
+    let data = CFData::from_vec(vec);
+    assert_eq!(data.GetBytePtr(), ptr);
+    assert_eq!(CFData::from_boxed(Box::new([4,5,6])).as_slice(), &[4,5,6]);
+    static STATIC: &[u8] = b"static";
+    assert_eq!(CFData::from_static(STATIC).GetBytePtr(), STATIC.as_ptr());
+}
+
+#[test] fn slice_traits() {
+    use std::collections::hash_map::DefaultHasher;
+    use std::io::{Read, Seek, SeekFrom};
+    let data = CFData::copy_slice(b"hello world");
+    assert_eq!(data.len(), 11);
+    assert!(*data == *b"hello world".as_slice());
+    assert!(*data == *CFData::from_str("hello world"));
+    let hash = |h: &dyn Fn(&mut DefaultHasher)| { let mut hasher = DefaultHasher::new(); h(&mut hasher); hasher.finish() };
+    assert_eq!(hash(&|h| data.hash(h)), hash(&|h| b"hello world".as_slice().hash(h)));
+
+    let mut cursor = data.cursor();
+    cursor.seek(SeekFrom::Start(6)).unwrap();
+    let mut s = String::new();
+    cursor.read_to_string(&mut s).unwrap();
+    assert_eq!(s, "world");
+
+    assert!(CFData::copy_slice(&[]).is_empty());
+}
+
+#[test] fn find() {
+    let data = CFData::copy_slice(b"abcabc");
+    let all = CFRange { location: 0, length: data.GetLength() };
+    let pattern = CFData::copy_slice(b"bc");
+    assert_eq!(data.find(&pattern, all, CFDataSearchFlags::None), Some(CFRange { location: 1, length: 2 }));
+    assert_eq!(data.find(&pattern, all, CFDataSearchFlags::Backwards), Some(CFRange { location: 4, length: 2 }));
+    assert_eq!(data.find(&pattern, all, CFDataSearchFlags::Anchored), None);
+    assert_eq!(data.find(&pattern, all, CFDataSearchFlags::Backwards | CFDataSearchFlags::Anchored), Some(CFRange { location: 4, length: 2 }));
+}
+
+#[test] #[should_panic(expected = "out of bounds")] fn range_overflow() {
+    let mut data = CFMutableData::new();
+    data.append(b"abc");
+    data.delete_bytes(CFRange { location: 1, length: CFIndex::MAX });
+}
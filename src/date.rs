@@ -0,0 +1,186 @@
+//!<CoreFoundation/CFDate.h>
+
+use std::cmp::Ordering;
+use std::ffi::c_void;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use crate::base::{CFAllocator, CFIndex};
+use crate::cell::StrongCell;
+use crate::ownership::CreateRule;
+use crate::error::DateOutOfRange;
+use crate::cf_type;
+
+///A number of seconds.
+pub type CFTimeInterval = f64;
+///Seconds relative to the reference date, 1 Jan 2001 00:00:00 GMT.
+pub type CFAbsoluteTime = CFTimeInterval;
+
+///Seconds from 1 Jan 1970 (the Unix epoch) to the reference date of [CFAbsoluteTime].
+#[allow(non_upper_case_globals)]
+pub const kCFAbsoluteTimeIntervalSince1970: CFTimeInterval = 978307200.0;
+
+cf_type!(
+    ///A point in time.  See cocoa docs for `CFDateRef`.
+    pub struct CFDate: CFDateGetTypeID, thread_safe
+);
+
+extern "C" {
+    fn CFAbsoluteTimeGetCurrent() -> CFAbsoluteTime;
+    fn CFDateCreate(allocator: *const CFAllocator, at: CFAbsoluteTime) -> CreateRule<CFDate>;
+    fn CFDateGetAbsoluteTime(theDate: *const CFDate) -> CFAbsoluteTime;
+    fn CFDateGetTimeIntervalSinceDate(theDate: *const CFDate, otherDate: *const CFDate) -> CFTimeInterval;
+    fn CFDateCompare(theDate: *const CFDate, otherDate: *const CFDate, context: *const c_void) -> CFIndex;
+}
+
+impl CFDate {
+    ///The current time, see cocoa docs for `CFAbsoluteTimeGetCurrent`.
+    pub fn current_absolute_time() -> CFAbsoluteTime {
+        unsafe { CFAbsoluteTimeGetCurrent() }
+    }
+    ///Creates a date for the current time.
+    pub fn now() -> StrongCell<CFDate> {
+        Self::from_absolute_time(Self::current_absolute_time())
+    }
+    ///Creates a date, see cocoa docs for `CFDateCreate`.
+    ///
+    /// # Panics
+    /// If CF fails to allocate the object, see [allocation failure](crate#allocation-failure).
+    pub fn from_absolute_time(at: CFAbsoluteTime) -> StrongCell<CFDate> {
+        Self::from_absolute_time_in(at, None)
+    }
+    ///Like [Self::from_absolute_time], using `allocator`, or the default allocator if `None`.
+    pub fn from_absolute_time_in(at: CFAbsoluteTime, allocator: Option<&CFAllocator>) -> StrongCell<CFDate> {
+        unsafe { CFDateCreate(CFAllocator::ptr_or_default(allocator), at) }.into_cell().expect("CFDateCreate returned NULL")
+    }
+    ///Creates a date from a [SystemTime].
+    pub fn from_system_time(time: SystemTime) -> StrongCell<CFDate> {
+        Self::from_system_time_in(time, None)
+    }
+    ///Like [Self::from_system_time], using `allocator`, or the default allocator if `None`.
+    pub fn from_system_time_in(time: SystemTime, allocator: Option<&CFAllocator>) -> StrongCell<CFDate> {
+        let (seconds, nanos) = match time.duration_since(UNIX_EPOCH) {
+            Ok(duration) => (duration.as_secs() as i64, duration.subsec_nanos()),
+            Err(e) => {
+                let duration = e.duration();
+                let seconds = -(duration.as_secs() as i64);
+                match duration.subsec_nanos() {
+                    0 => (seconds, 0),
+                    nanos => (seconds - 1, 1_000_000_000 - nanos),
+                }
+            }
+        };
+        Self::from_unix_timestamp_in(seconds, nanos, allocator)
+    }
+    ///Seconds since the reference date, see cocoa docs for `CFDateGetAbsoluteTime`.
+    pub fn absolute_time(&self) -> CFAbsoluteTime {
+        unsafe { CFDateGetAbsoluteTime(self) }
+    }
+    ///Seconds from `other` to `self`, negative if `self` is earlier.  See cocoa docs for `CFDateGetTimeIntervalSinceDate`.
+    pub fn time_interval_since(&self, other: &CFDate) -> CFTimeInterval {
+        unsafe { CFDateGetTimeIntervalSinceDate(self, other) }
+    }
+    ///Converts to a [SystemTime], or `None` if the date is not finite or out of range for [SystemTime].
+    ///
+    /// CF stores dates as `f64` seconds, so the result is only as precise as that (about a microsecond for present-day dates).
+    pub fn to_system_time(&self) -> Option<SystemTime> {
+        let (seconds, nanos) = self.unix_timestamp()?;
+        let whole = if seconds >= 0 {
+            UNIX_EPOCH.checked_add(Duration::from_secs(seconds as u64))
+        }
+        else {
+            UNIX_EPOCH.checked_sub(Duration::from_secs(seconds.unsigned_abs()))
+        };
+        whole?.checked_add(Duration::from_nanos(nanos as u64))
+    }
+    ///Splits into whole seconds since the Unix epoch and nanoseconds, rounded to the nearest nanosecond.
+    ///
+    /// Returns `None` if the time is not finite or doesn't fit.
+    pub(crate) fn unix_timestamp(&self) -> Option<(i64, u32)> {
+        let at = self.absolute_time();
+        if !at.is_finite() || at.abs() >= i64::MAX as f64 / 2.0 {
+            return None
+        }
+        let seconds = at.floor();
+        let nanos = ((at - seconds) * 1e9).round() as u32;
+        let seconds = seconds as i64 + kCFAbsoluteTimeIntervalSince1970 as i64;
+        //the fraction can round up to a whole second
+        if nanos == 1_000_000_000 { Some((seconds + 1, 0)) } else { Some((seconds, nanos)) }
+    }
+    ///Creates a date from seconds since the Unix epoch and nanoseconds, inverting [Self::unix_timestamp].
+    #[cfg(any(feature = "chrono", feature = "time"))]
+    pub(crate) fn from_unix_timestamp(seconds: i64, nanos: u32) -> StrongCell<CFDate> {
+        Self::from_unix_timestamp_in(seconds, nanos, None)
+    }
+    ///Like [Self::from_unix_timestamp], using `allocator`, or the default allocator if `None`.
+    ///
+    /// Subtracting the reference date in whole seconds before adding the fraction rounds only once.
+    pub(crate) fn from_unix_timestamp_in(seconds: i64, nanos: u32, allocator: Option<&CFAllocator>) -> StrongCell<CFDate> {
+        let seconds = seconds - kCFAbsoluteTimeIntervalSince1970 as i64;
+        Self::from_absolute_time_in(seconds as f64 + nanos as f64 / 1e9, allocator)
+    }
+}
+
+///Orders by time, see cocoa docs for `CFDateCompare`.
+impl PartialOrd for CFDate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl Ord for CFDate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        unsafe { CFDateCompare(self, other, std::ptr::null()) }.cmp(&0)
+    }
+}
+
+impl From<SystemTime> for StrongCell<CFDate> {
+    fn from(time: SystemTime) -> Self {
+        CFDate::from_system_time(time)
+    }
+}
+impl TryFrom<&CFDate> for SystemTime {
+    type Error = DateOutOfRange;
+
+    fn try_from(date: &CFDate) -> Result<Self, Self::Error> {
+        date.to_system_time().ok_or(DateOutOfRange { absolute_time: date.absolute_time() })
+    }
+}
+
+#[cfg(test)] mod tests {
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+    use crate::DateOutOfRange;
+    use super::{CFDate, kCFAbsoluteTimeIntervalSince1970};
+
+    #[test] fn reference_date() {
+        let reference = CFDate::from_absolute_time(0.0);
+        assert_eq!(reference.to_system_time(), Some(UNIX_EPOCH + Duration::from_secs(978307200)));
+        let epoch = CFDate::from_system_time(UNIX_EPOCH);
+        assert_eq!(epoch.absolute_time(), -kCFAbsoluteTimeIntervalSince1970);
+        let before_epoch = CFDate::from_system_time(UNIX_EPOCH - Duration::from_secs(10));
+        assert_eq!(before_epoch.to_system_time(), Some(UNIX_EPOCH - Duration::from_secs(10)));
+        let before_epoch = CFDate::from_system_time(UNIX_EPOCH - Duration::from_millis(10_250));
+        assert_eq!(before_epoch.absolute_time(), -kCFAbsoluteTimeIntervalSince1970 - 10.25);
+        assert_eq!(SystemTime::try_from(&*before_epoch), Ok(UNIX_EPOCH - Duration::from_millis(10_250)));
+    }
+
+    #[test] fn out_of_range() {
+        assert_eq!(CFDate::from_absolute_time(f64::NAN).to_system_time(), None);
+        assert_eq!(CFDate::from_absolute_time(f64::INFINITY).to_system_time(), None);
+        let far_future = CFDate::from_absolute_time(1e300);
+        assert_eq!(SystemTime::try_from(&*far_future), Err(DateOutOfRange { absolute_time: 1e300 }));
+    }
+
+    #[test] fn compare() {
+        let earlier = CFDate::from_absolute_time(100.0);
+        let later = CFDate::from_absolute_time(160.5);
+        assert!(*earlier < *later);
+        assert_eq!(later.time_interval_since(&earlier), 60.5);
+        assert!(*CFDate::now() > *later);
+    }
+
+    #[test] fn plist_date() {
+        use crate::{CFData, CFPropertyList};
+        let plist = r#"<?xml version="1.0" encoding="UTF-8"?>
+<plist version="1.0"><date>2001-01-01T00:01:00Z</date></plist>"#;
+        let date: crate::StrongCell<CFDate> = CFPropertyList::from_data(&CFData::from_str(plist)).unwrap().cast_checked();
+        assert_eq!(date.absolute_time(), 60.0);
+    }
+}
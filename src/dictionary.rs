@@ -1,28 +1,82 @@
-use crate::base::{CFType, CFTypeID, CFTypeWithBaseType, CFTypeAny, OpaqueCType};
+use crate::base::{CFType, CFShared, CFMutableCopy, CFTypeAny, CFAllocator, CFIndex};
+use crate::cf_type;
 use std::ffi::c_void;
+use crate::cell::{StrongCell, StrongMutCell};
+use crate::ownership::{CreateRule, GetRule};
 use crate::CFTypeBehavior;
 
+cf_type!(
+    ///A dictionary of CF objects.  See cocoa docs for `CFDictionaryRef`.
+    ///
+    /// Keys and values may be of any type, including ones that aren't [crate::CFThreadSafe], so dictionaries stay on one thread:
+    /// ```compile_fail
+    /// fn assert_send<S: Send>() {}
+    /// assert_send::<core_foundationr::StrongCell<core_foundationr::CFDictionary>>();
+    /// ```
+    pub struct CFDictionary: CFDictionaryGetTypeID,
+    mutable
+    ///A mutable dictionary.  See cocoa docs for `CFMutableDictionaryRef`.
+    ///
+    /// Mutating methods take `&mut self`, so these are usually held in a [StrongMutCell].
+    pub struct CFMutableDictionary
+);
+///`CFDictionaryKeyCallBacks`.  We only use CF's predefined callbacks, so the fields are opaque.
 #[repr(C)]
-pub struct CFDictionary(OpaqueCType);
-impl CFType for CFDictionary {}
+struct CFDictionaryKeyCallBacks([usize; 6]);
+///`CFDictionaryValueCallBacks`.  We only use CF's predefined callbacks, so the fields are opaque.
+#[repr(C)]
+struct CFDictionaryValueCallBacks([usize; 5]);
+
 extern "C" {
-    fn CFDictionaryGetTypeID() -> CFTypeID;
-    fn CFDictionaryGetValue(theDict: *const CFDictionary, key: *const c_void) -> *const CFTypeAny;
+    static kCFTypeDictionaryKeyCallBacks: CFDictionaryKeyCallBacks;
+    static kCFTypeDictionaryValueCallBacks: CFDictionaryValueCallBacks;
+    fn CFDictionaryCreateMutableCopy(allocator: *const CFAllocator, capacity: CFIndex, theDict: *const CFDictionary) -> CreateRule<CFMutableDictionary>;
+    fn CFDictionaryCreateMutable(allocator: *const CFAllocator, capacity: CFIndex, keyCallBacks: *const CFDictionaryKeyCallBacks, valueCallBacks: *const CFDictionaryValueCallBacks) -> CreateRule<CFMutableDictionary>;
+    fn CFDictionarySetValue(theDict: *const CFMutableDictionary, key: *const c_void, value: *const c_void);
+    fn CFDictionaryRemoveValue(theDict: *const CFMutableDictionary, key: *const c_void);
+    fn CFDictionaryCreate(allocator: *const CFAllocator, keys: *const *const c_void, values: *const *const c_void, numValues: CFIndex, keyCallBacks: *const CFDictionaryKeyCallBacks, valueCallBacks: *const CFDictionaryValueCallBacks) -> CreateRule<CFDictionary>;
+    fn CFDictionaryGetValue<'a>(theDict: &'a CFDictionary, key: *const c_void) -> GetRule<'a, CFTypeAny>;
 }
 
-impl CFTypeWithBaseType for CFDictionary {
-    fn type_id() -> CFTypeID {
-        unsafe { CFDictionaryGetTypeID() }
+impl CFMutableCopy for CFDictionary {
+    type Mutable = CFMutableDictionary;
+    fn mutable_copy_in(&self, allocator: Option<&CFAllocator>) -> StrongMutCell<CFMutableDictionary> {
+        unsafe {
+            let raw = CFDictionaryCreateMutableCopy(CFAllocator::ptr_or_default(allocator), 0, self);
+            raw.into_cell().expect("CFDictionaryCreateMutableCopy returned NULL").assuming_mut()
+        }
+    }
+}
+impl CFMutableCopy for CFMutableDictionary {
+    type Mutable = CFMutableDictionary;
+    fn mutable_copy_in(&self, allocator: Option<&CFAllocator>) -> StrongMutCell<CFMutableDictionary> {
+        self.as_immutable().mutable_copy_in(allocator)
     }
 }
 
 impl CFDictionary {
+    ///Creates a dictionary from key-value pairs, which are retained by the dictionary.  See cocoa docs for `CFDictionaryCreate`.
+    ///
+    /// # Panics
+    /// If CF fails to allocate the object, see [allocation failure](crate#allocation-failure).
+    pub fn from_pairs<K: CFShared, V: CFShared>(pairs: &[(&K, &V)]) -> StrongCell<CFDictionary> {
+        CFDictionary::from_pairs_in(pairs, None)
+    }
+    ///Like [Self::from_pairs], using `allocator`, or the default allocator if `None`.
+    pub fn from_pairs_in<K: CFShared, V: CFShared>(pairs: &[(&K, &V)], allocator: Option<&CFAllocator>) -> StrongCell<CFDictionary> {
+        let keys: Vec<*const c_void> = pairs.iter().map(|(k, _)| k.as_ptr()).collect();
+        let values: Vec<*const c_void> = pairs.iter().map(|(_, v)| v.as_ptr()).collect();
+        unsafe {
+            let raw = CFDictionaryCreate(CFAllocator::ptr_or_default(allocator), keys.as_ptr(), values.as_ptr(), pairs.len().try_into().unwrap(), &kCFTypeDictionaryKeyCallBacks, &kCFTypeDictionaryValueCallBacks);
+            raw.into_cell().expect("CFDictionaryCreate returned NULL")
+        }
+    }
     ///Gets the given key, using raw pointers.
     ///
     /// The return value may be null.  In this case, either the key is not present in the dictionary,
     /// or it is present and has the explicit value NULL.
     pub unsafe fn get_with_ptr(&self, key: *const c_void) -> *const CFTypeAny {
-        &*CFDictionaryGetValue(self, key)
+        CFDictionaryGetValue(self, key).get().map_or(std::ptr::null(), |v| v)
     }
 
     ///Gets the given key, using some [CFType] key.
@@ -30,15 +84,73 @@ impl CFDictionary {
     /// The return value may be None.  In this case, either the key is not present in the dictionary,
     /// or it is present and has the explicit value NULL.
     /// The return value has the lifetime of the receiver
-    pub fn get_with_key<K: CFType>(&self, key: &K) -> Option<&CFTypeAny> {
+    pub fn get_with_key(&self, key: impl AsRef<CFTypeAny>) -> Option<&CFTypeAny> {
+        unsafe{ CFDictionaryGetValue(self, key.as_ref().as_ptr()) }.get()
+    }
+}
+
+impl CFMutableDictionary {
+    ///Creates an empty mutable dictionary, see cocoa docs for `CFDictionaryCreateMutable`.
+    ///
+    /// # Panics
+    /// If CF fails to allocate the object, see [allocation failure](crate#allocation-failure).
+    pub fn new() -> StrongMutCell<CFMutableDictionary> {
+        Self::new_in(None)
+    }
+    ///Like [Self::new], using `allocator`, or the default allocator if `None`.
+    pub fn new_in(allocator: Option<&CFAllocator>) -> StrongMutCell<CFMutableDictionary> {
         unsafe {
-            let result = self.get_with_ptr(key.as_ptr());
-            if result.is_null() {
-                None
-            }
-            else {
-                Some(&*result)
-            }
+            let raw = CFDictionaryCreateMutable(CFAllocator::ptr_or_default(allocator), 0, &kCFTypeDictionaryKeyCallBacks, &kCFTypeDictionaryValueCallBacks);
+            raw.into_cell().expect("CFDictionaryCreateMutable returned NULL").assuming_mut()
         }
     }
-}
\ No newline at end of file
+    ///Sets the value for `key`, replacing any existing value.  Both are retained.  See cocoa docs for `CFDictionarySetValue`.
+    pub fn set<K: CFShared, V: CFShared>(&mut self, key: &K, value: &V) {
+        unsafe { CFDictionarySetValue(self, key.as_ptr(), value.as_ptr()) }
+    }
+    ///Like [CFDictionary::get_with_key].  The return value borrows the dictionary, so it can't outlive a mutation.
+    pub fn get_with_key(&self, key: impl AsRef<CFTypeAny>) -> Option<&CFTypeAny> {
+        self.as_immutable().get_with_key(key)
+    }
+    ///Removes the value for `key`, if any.  See cocoa docs for `CFDictionaryRemoveValue`.
+    pub fn remove<K: CFType>(&mut self, key: &K) {
+        unsafe { CFDictionaryRemoveValue(self, key.as_ptr()) }
+    }
+}
+
+#[test] fn from_pairs() {
+    use crate::{CFString, CFData};
+    let key = CFString::from_str("key");
+    let value = CFData::copy_slice(b"value");
+    let dictionary = CFDictionary::from_pairs(&[(&*key, &*value)]);
+    let found = dictionary.get_with_key(&*key).unwrap();
+    assert_eq!(found.as_ptr(), value.as_ptr());
+    assert!(dictionary.get_with_key(&*CFString::from_str("missing")).is_none());
+}
+
+#[test] fn mutable() {
+    use crate::{CFString, CFData};
+    let key = CFString::from_str("key");
+    let value = CFData::copy_slice(b"value");
+    let mut dictionary = CFDictionary::from_pairs::<CFString, CFData>(&[]).mutable_copy();
+    dictionary.set(&*key, &*value);
+    assert_eq!(dictionary.get_with_key(&*key).unwrap().as_ptr(), value.as_ptr());
+    let copy = dictionary.copy();
+    dictionary.remove(&*key);
+    assert!(dictionary.get_with_key(&*key).is_none());
+    let immutable: StrongCell<CFDictionary> = copy.upcast();
+    assert!(immutable.get_with_key(&*key).is_some());
+    assert!(CFMutableDictionary::new().get_with_key(&*key).is_none());
+}
+
+#[test] fn any_key() {
+    use crate::{CFString, CFNumber};
+    let key = CFString::from_str("key");
+    let value = CFNumber::from_i64(1);
+    let mut dictionary = CFMutableDictionary::new();
+    dictionary.set(key.as_any(), value.as_any());
+    //keys can be passed as references, cells or CFTypeAny
+    assert!(dictionary.get_with_key(&*key).is_some());
+    assert!(dictionary.get_with_key(&key).is_some());
+    assert!(dictionary.get_with_key(key.clone().into_any()).is_some());
+}
@@ -1,30 +1,84 @@
 use std::fmt::{Formatter};
-use crate::base::{CFType, OpaqueCType};
+use crate::base::CFTypeID;
+use crate::ownership::CreateRule;
+use crate::cf_type;
 use crate::{CFString, StrongCell};
 
-#[repr(C)]
-#[derive(Debug)]
-pub struct CFError(OpaqueCType);
-impl CFType for CFError {}
+cf_type!(pub struct CFError: CFErrorGetTypeID, thread_safe);
 
 pub type CFErrorDomain = CFString;
 
 extern "C" {
-    fn CFErrorCopyDescription(error: *const CFError) -> *const CFString;
+    fn CFErrorCopyDescription(error: *const CFError) -> CreateRule<CFString>;
+    fn CFCopyTypeIDDescription(type_id: CFTypeID) -> CreateRule<CFString>;
 }
 
 impl std::fmt::Display for CFError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         unsafe {
-            let description = CFErrorCopyDescription(self);
             //documented to never be null.
-            let description_ref = StrongCell::assuming_retained_nonnull(description);
+            let description_ref = CFErrorCopyDescription(self).into_cell().expect("CFErrorCopyDescription returned NULL");
             //todo: could potentially be optimized with an internal pointer perhaps
             f.write_fmt(format_args!("{}",description_ref.as_string()))
         }
     }
 }
 impl std::error::Error for CFError {}
+
+///A cast failed because the object has a different type.
+///
+/// See [crate::CFTypeBehavior::try_cast_ref] and [StrongCell::try_cast].
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub struct TypeMismatch {
+    pub expected: CFTypeID,
+    pub actual: CFTypeID,
+}
+impl TypeMismatch {
+    ///Name of the expected type, like `CFString`.  See cocoa docs for `CFCopyTypeIDDescription`.
+    ///
+    /// # Panics
+    /// If CF fails to allocate the object, see [allocation failure](crate#allocation-failure).
+    pub fn expected_name(&self) -> StrongCell<CFString> {
+        unsafe { CFCopyTypeIDDescription(self.expected) }.into_cell().expect("CFCopyTypeIDDescription returned NULL")
+    }
+    ///Name of the actual type, like `CFNumber`.  See cocoa docs for `CFCopyTypeIDDescription`.
+    ///
+    /// # Panics
+    /// If CF fails to allocate the object, see [allocation failure](crate#allocation-failure).
+    pub fn actual_name(&self) -> StrongCell<CFString> {
+        unsafe { CFCopyTypeIDDescription(self.actual) }.into_cell().expect("CFCopyTypeIDDescription returned NULL")
+    }
+}
+impl std::fmt::Display for TypeMismatch {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("expected {} (type ID {}), found {} (type ID {})", self.expected_name(), self.expected, self.actual_name(), self.actual))
+    }
+}
+impl std::error::Error for TypeMismatch {}
+///A [crate::CFDate] can't be represented in another date type, because it is out of range or not finite.
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub struct DateOutOfRange {
+    pub absolute_time: crate::CFAbsoluteTime,
+}
+impl std::fmt::Display for DateOutOfRange {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("absolute time {} is out of range", self.absolute_time))
+    }
+}
+impl std::error::Error for DateOutOfRange {}
+
+///A time zone name is not known on the other side of a conversion.
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub struct UnknownTimeZone {
+    pub name: String,
+}
+impl std::fmt::Display for UnknownTimeZone {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("unknown time zone {}", self.name))
+    }
+}
+impl std::error::Error for UnknownTimeZone {}
+
 #[cfg(test)] mod tests {
     use std::ops::Deref;
     use crate::base::CFIndex;
@@ -47,4 +101,20 @@ impl std::error::Error for CFError {}
             assert_eq!(error,"The operation couldn’t be completed. (test error 0.)")
         }
     }
+
+    #[test] fn type_mismatch() {
+        use crate::{CFTypeBehavior, CFTypeWithBaseType, CFArray, CFData};
+        let string = CFString::from_str("test");
+        let any = string.as_ptr();
+        let any: &crate::CFTypeAny = unsafe{ &*crate::CFTypeAny::from_ptr(any) };
+        let mismatch = any.try_cast_ref::<CFArray>().err().unwrap();
+        assert_eq!(mismatch.expected_name().as_string(), "CFArray");
+        assert_eq!(mismatch.actual_name().as_string(), "CFString");
+        assert!(format!("{}", mismatch).starts_with("expected CFArray"));
+        assert!(any.try_cast_ref::<CFString>().is_ok());
+
+        let (string, mismatch) = string.try_cast::<CFData>().unwrap_err();
+        assert_eq!(mismatch.actual, <CFString as CFTypeWithBaseType>::type_id());
+        assert_eq!(string.try_cast::<CFString>().unwrap().as_string(), "test");
+    }
 }
@@ -0,0 +1,77 @@
+use crate::base::{CFType, CFTypeAny, CFTypeID, CFTypeWithBaseType, CFNull};
+use crate::number::{CFNumber, CFBoolean};
+use crate::date::CFDate;
+use crate::{CFString, CFData, CFArray, CFDictionary, CFTypeBehavior};
+
+///The concrete type of a [CFTypeAny], as returned by [CFTypeAny::classify].
+///
+/// This covers every property list type, so a single `match` can walk a [crate::CFPropertyList].
+#[derive(Clone,Copy)]
+pub enum CFKind<'a> {
+    String(&'a CFString),
+    Data(&'a CFData),
+    Array(&'a CFArray),
+    Dictionary(&'a CFDictionary),
+    Number(&'a CFNumber),
+    Boolean(&'a CFBoolean),
+    Date(&'a CFDate),
+    Null(&'a CFNull),
+    ///Any other type
+    Other(&'a CFTypeAny),
+}
+
+impl CFTypeAny {
+    ///Determines the concrete type of the object.
+    pub fn classify(&self) -> CFKind<'_> {
+        fn is<T: CFTypeWithBaseType>(type_id: CFTypeID) -> bool {
+            T::type_id() == type_id
+        }
+        //only called once the type is known to match
+        fn cast<T: CFType>(any: &CFTypeAny) -> &T {
+            unsafe{ &*T::from_ptr(any.as_ptr()) }
+        }
+        let type_id = CFTypeBehavior::type_id(self);
+        if is::<CFString>(type_id) { CFKind::String(cast(self)) }
+        else if is::<CFData>(type_id) { CFKind::Data(cast(self)) }
+        else if is::<CFArray>(type_id) { CFKind::Array(cast(self)) }
+        else if is::<CFDictionary>(type_id) { CFKind::Dictionary(cast(self)) }
+        else if is::<CFNumber>(type_id) { CFKind::Number(cast(self)) }
+        else if is::<CFBoolean>(type_id) { CFKind::Boolean(cast(self)) }
+        else if is::<CFDate>(type_id) { CFKind::Date(cast(self)) }
+        else if is::<CFNull>(type_id) { CFKind::Null(cast(self)) }
+        else { CFKind::Other(self) }
+    }
+}
+
+#[test] fn classify() {
+    use crate::CFPropertyList;
+    let str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<array>
+	<string>hello</string>
+	<data>AAEC</data>
+	<array/>
+	<dict/>
+	<integer>42</integer>
+	<true/>
+	<date>2021-01-01T00:00:00Z</date>
+</array>
+</plist>"#;
+    let list = CFPropertyList::from_data(&CFData::from_str(str)).unwrap();
+    let array: &CFArray = list.checked_cast();
+    let kinds: Vec<&str> = array.iter().map(|item| match item.classify() {
+        CFKind::String(s) => { assert_eq!(s.as_string(), "hello"); "string" }
+        CFKind::Data(d) => { assert_eq!(d.as_slice(), &[0, 1, 2]); "data" }
+        CFKind::Array(_) => "array",
+        CFKind::Dictionary(_) => "dictionary",
+        CFKind::Number(n) => { assert_eq!(n.as_i64(), Some(42)); "number" }
+        CFKind::Boolean(b) => { assert!(b.value()); "boolean" }
+        CFKind::Date(_) => "date",
+        CFKind::Null(_) => "null",
+        CFKind::Other(_) => "other",
+    }).collect();
+    assert_eq!(kinds, vec!["string", "data", "array", "dictionary", "number", "boolean", "date"]);
+    let null: &CFTypeAny = unsafe{ &*CFTypeAny::from_ptr(CFNull::null().as_ptr()) };
+    assert!(matches!(null.classify(), CFKind::Null(_)));
+}
@@ -14,8 +14,17 @@ for macOS low-level libraries.
 # Design notes:
 * We use opaque types to model the underlying CFType (like `__CFString`, etc.)  Then the equivalent to `CFStringRef` is
   `&CFString`, a pointer type.
+* Functions are declared to return [ownership::CreateRule] or [ownership::GetRule], according to the CF ownership rule they follow.
+  These convert to `StrongCell<T>` or `&'a T`, so bindings don't handle ownership by hand.
+* Types from other frameworks (CoreText, Security, etc.) can be declared with [cf_type!].
 * Some optimizations are not yet implemented.  Among them, optimizations for static-time strings, inner pointers, zero-copy, etc.
 
+# Allocation failure
+Constructors that return `StrongCell` (rather than `Option` or `Result`) panic if CF returns NULL.  For these,
+the arguments are always valid (e.g. a `&str` is always valid UTF-8), so NULL only means CF couldn't allocate
+the object, which like `Vec` we treat as unrecoverable.  Constructors that can fail on their input, like
+[time_zone::CFTimeZone::from_name], return `Option` or `Result` instead.
+
 # Implementation status
 
 The following types are at least partially implemented.  Usually they contain "common APIs" or "the ones I use".
@@ -25,21 +34,52 @@ The following types are at least partially implemented.  Usually they contain "c
 * [base::CFRange]
 * [base::CFType]
 * [base::CFAllocator]
+* [base::CFNull]
+* [kind::CFKind], from [base::CFTypeAny::classify]
 
 ## Strings
 * [base::CFString]
+* [string::CFMutableString]
+* [string_tokenizer::CFStringTokenizer]
+
+## Locale
+* [locale::CFLocale]
+* [number_formatter::CFNumberFormatter]
+
+## Numbers
+* [number::CFNumberType]
+* [number::CFNumber]
+* [number::CFBoolean]
+
+## Dates
+* [date::CFDate]
+* [date::CFAbsoluteTime], [date::CFTimeInterval]
+* [time_zone::CFTimeZone]
+* [calendar::CFCalendar], [calendar::CFCalendarComponents]
+
+With the `chrono` feature, `CFDate` converts to and from `chrono::DateTime`, and `CFTimeZone` to and from `chrono_tz::Tz`.
+With the `time` feature, `CFDate` converts to and from `time::OffsetDateTime`.
 
 ## Arrays
 * [array::CFArray]
+* [array::CFMutableArray]
 
 ## Pointers
 * [cell::StrongCell]
+* [weak::WeakCell]
+
+## Allocators
+* [allocation_tracker::CFAllocationTracker], with [allocation_tracker::assert_no_cf_leaks] for leak tests
 
 ## Data
 * [data::CFData]
+* [data::CFMutableData]
+
+With the `bytes` feature, `StrongCell<CFData>` converts to and from `bytes::Bytes` without copying.
 
 ## Dictionary
 * [dictionary::CFDictionary]
+* [dictionary::CFMutableDictionary]
 
 ## Error
 * [error::CFError]
@@ -52,22 +92,57 @@ The following types are at least partially implemented.  Usually they contain "c
 
  */
 mod base;
+mod allocator;
+mod allocation_tracker;
 mod string;
 mod cell;
+mod weak;
+mod ownership;
 mod data;
 mod property_list;
 mod error;
 mod dictionary;
 mod array;
-
-
-pub use string::CFStringEncoding;
-pub use base::{CFString,CFOptionFlags,CFTypeID,CFRange,CFType,CFAllocator,CFTypeAny};
+mod locale;
+mod string_tokenizer;
+mod number;
+mod number_formatter;
+mod date;
+mod time_zone;
+mod calendar;
+mod kind;
+#[cfg(feature = "bytes")]
+mod bytes;
+#[cfg(feature = "chrono")]
+mod chrono;
+#[cfg(feature = "time")]
+mod time;
+
+
+pub use string::{CFStringEncoding,CFMutableString};
+pub use base::{CFString,CFOptionFlags,CFTypeID,CFRange,CFType,CFAllocator,CFTypeAny,CFNull,CFIndex,CFHashCode,kCFNotFound};
 pub use property_list::{MutabilityOptions,Format};
-pub use data::CFData;
+pub use data::{CFData,CFMutableData,CFDataSearchFlags};
 pub use property_list::CFPropertyList;
-pub use base::CFTypeBehavior;
-pub use dictionary::CFDictionary;
-pub use array::CFArray;
+pub use base::{CFTypeBehavior,CFTypeWithBaseType,CFMutableCopy,CFThreadSafe,CFSubtype,CFShared};
+pub use dictionary::{CFDictionary,CFMutableDictionary};
+pub use array::{CFArray,CFMutableArray};
+pub use locale::CFLocale;
+pub use number::{CFNumberType,CFNumber,CFBoolean};
+pub use date::{CFDate,CFAbsoluteTime,CFTimeInterval,kCFAbsoluteTimeIntervalSince1970};
+pub use time_zone::CFTimeZone;
+pub use calendar::{CFCalendar,CFCalendarUnit,CFCalendarComponents};
+pub use kind::CFKind;
+pub use number_formatter::{CFNumberFormatter,CFNumberFormatterStyle};
+pub use string_tokenizer::{CFStringTokenizer,CFStringTokenizerUnit,CFStringTokenizerAttribute,CFStringTokenizerTokenType,CFStringTokenizerRanges};
 pub use error::*;
-pub use cell::*;
\ No newline at end of file
+pub use cell::*;
+pub use weak::WeakCell;
+pub use ownership::{CreateRule, GetRule};
+pub use allocation_tracker::{CFAllocationTracker,LiveAllocation,assert_no_cf_leaks};
+
+///Items used by exported macros.  Not public API.
+#[doc(hidden)]
+pub mod __private {
+    pub use crate::base::{OpaqueCType, cf_equal, cf_hash};
+}
\ No newline at end of file
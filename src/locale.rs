@@ -0,0 +1,52 @@
+//!<CoreFoundation/CFLocale.h>
+
+use crate::base::CFAllocator;
+use crate::cf_type;
+use crate::cell::StrongCell;
+use crate::ownership::{CreateRule, GetRule};
+use crate::CFString;
+
+cf_type!(pub struct CFLocale: CFLocaleGetTypeID, thread_safe);
+
+extern "C" {
+    fn CFLocaleCopyCurrent() -> CreateRule<CFLocale>;
+    fn CFLocaleGetSystem() -> GetRule<'static, CFLocale>;
+    fn CFLocaleCreate(allocator: *const CFAllocator, localeIdentifier: *const CFString) -> CreateRule<CFLocale>;
+    fn CFLocaleGetIdentifier<'a>(locale: &'a CFLocale) -> GetRule<'a, CFString>;
+}
+
+impl CFLocale {
+    ///The user's current locale, see cocoa docs for `CFLocaleCopyCurrent`.
+    ///
+    /// # Panics
+    /// If CF fails to allocate the object, see [allocation failure](crate#allocation-failure).
+    pub fn current() -> StrongCell<CFLocale> {
+        unsafe { CFLocaleCopyCurrent() }.into_cell().expect("CFLocaleCopyCurrent returned NULL")
+    }
+    ///The root, locale-independent locale, see cocoa docs for `CFLocaleGetSystem`.
+    pub fn system() -> StrongCell<CFLocale> {
+        unsafe { CFLocaleGetSystem() }.retain().expect("CFLocaleGetSystem returned NULL")
+    }
+    ///Creates a locale for the given identifier, like `en_US` or `de_DE`.
+    ///
+    /// Unknown identifiers produce a locale with default behavior, rather than an error.
+    ///
+    /// # Panics
+    /// If CF fails to allocate the object, see [allocation failure](crate#allocation-failure).
+    pub fn from_identifier(identifier: &CFString) -> StrongCell<CFLocale> {
+        Self::from_identifier_in(identifier, None)
+    }
+    ///Like [Self::from_identifier], using `allocator`, or the default allocator if `None`.
+    pub fn from_identifier_in(identifier: &CFString, allocator: Option<&CFAllocator>) -> StrongCell<CFLocale> {
+        unsafe { CFLocaleCreate(CFAllocator::ptr_or_default(allocator), identifier) }.into_cell().expect("CFLocaleCreate returned NULL")
+    }
+    ///The canonical identifier for the locale.  The return value has the lifetime of the receiver.
+    pub fn identifier(&self) -> &CFString {
+        unsafe { CFLocaleGetIdentifier(self) }.get().expect("CFLocaleGetIdentifier returned NULL")
+    }
+}
+
+#[test] fn identifier() {
+    let locale = CFLocale::from_identifier(&CFString::from_str("de_DE"));
+    assert_eq!(locale.identifier().as_string(), "de_DE");
+}
@@ -0,0 +1,127 @@
+//!<CoreFoundation/CFNumber.h>
+
+use std::ffi::c_void;
+use crate::base::{CFAllocator, CFIndex};
+use crate::cf_type;
+use crate::cell::StrongCell;
+use crate::ownership::CreateRule;
+
+///Describes the C type of a number value.
+#[repr(transparent)]
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub struct CFNumberType(CFIndex);
+#[allow(non_upper_case_globals)]
+impl CFNumberType {
+    pub const SInt8Type: CFNumberType = CFNumberType(1);
+    pub const SInt16Type: CFNumberType = CFNumberType(2);
+    pub const SInt32Type: CFNumberType = CFNumberType(3);
+    pub const SInt64Type: CFNumberType = CFNumberType(4);
+    pub const Float32Type: CFNumberType = CFNumberType(5);
+    pub const Float64Type: CFNumberType = CFNumberType(6);
+    pub const CharType: CFNumberType = CFNumberType(7);
+    pub const ShortType: CFNumberType = CFNumberType(8);
+    pub const IntType: CFNumberType = CFNumberType(9);
+    pub const LongType: CFNumberType = CFNumberType(10);
+    pub const LongLongType: CFNumberType = CFNumberType(11);
+    pub const FloatType: CFNumberType = CFNumberType(12);
+    pub const DoubleType: CFNumberType = CFNumberType(13);
+    pub const CFIndexType: CFNumberType = CFNumberType(14);
+    pub const NSIntegerType: CFNumberType = CFNumberType(15);
+    pub const CGFloatType: CFNumberType = CFNumberType(16);
+}
+
+cf_type!(pub struct CFBoolean: CFBooleanGetTypeID, thread_safe);
+
+cf_type!(pub struct CFNumber: CFNumberGetTypeID, thread_safe);
+
+extern "C" {
+    static kCFBooleanTrue: *const CFBoolean;
+    static kCFBooleanFalse: *const CFBoolean;
+    fn CFBooleanGetValue(boolean: *const CFBoolean) -> bool;
+    fn CFNumberCreate(allocator: *const CFAllocator, theType: CFNumberType, valuePtr: *const c_void) -> CreateRule<CFNumber>;
+    fn CFNumberGetType(number: *const CFNumber) -> CFNumberType;
+    fn CFNumberIsFloatType(number: *const CFNumber) -> bool;
+    fn CFNumberGetValue(number: *const CFNumber, theType: CFNumberType, valuePtr: *mut c_void) -> bool;
+}
+
+impl CFBoolean {
+    ///Returns `kCFBooleanTrue` or `kCFBooleanFalse`.
+    pub fn from_bool(value: bool) -> &'static CFBoolean {
+        unsafe {
+            if value { &*kCFBooleanTrue } else { &*kCFBooleanFalse }
+        }
+    }
+    pub fn value(&self) -> bool {
+        unsafe { CFBooleanGetValue(self) }
+    }
+}
+
+impl CFNumber {
+    ///Creates a number, see cocoa docs for `CFNumberCreate`.
+    ///
+    /// # Panics
+    /// If CF fails to allocate the object, see [allocation failure](crate#allocation-failure).
+    pub fn from_i64(value: i64) -> StrongCell<CFNumber> {
+        CFNumber::from_i64_in(value, None)
+    }
+    ///Like [Self::from_i64], using `allocator`, or the default allocator if `None`.
+    pub fn from_i64_in(value: i64, allocator: Option<&CFAllocator>) -> StrongCell<CFNumber> {
+        unsafe {
+            let raw = CFNumberCreate(CFAllocator::ptr_or_default(allocator), CFNumberType::SInt64Type, &value as *const i64 as *const c_void);
+            raw.into_cell().expect("CFNumberCreate returned NULL")
+        }
+    }
+    ///Creates a number, see cocoa docs for `CFNumberCreate`.
+    ///
+    /// # Panics
+    /// If CF fails to allocate the object, see [allocation failure](crate#allocation-failure).
+    pub fn from_f64(value: f64) -> StrongCell<CFNumber> {
+        CFNumber::from_f64_in(value, None)
+    }
+    ///Like [Self::from_f64], using `allocator`, or the default allocator if `None`.
+    pub fn from_f64_in(value: f64, allocator: Option<&CFAllocator>) -> StrongCell<CFNumber> {
+        unsafe {
+            let raw = CFNumberCreate(CFAllocator::ptr_or_default(allocator), CFNumberType::Float64Type, &value as *const f64 as *const c_void);
+            raw.into_cell().expect("CFNumberCreate returned NULL")
+        }
+    }
+    ///The type the number was created with, see cocoa docs for `CFNumberGetType`.
+    pub fn number_type(&self) -> CFNumberType {
+        unsafe { CFNumberGetType(self) }
+    }
+    ///Whether the number holds a floating-point value.
+    pub fn is_float_type(&self) -> bool {
+        unsafe { CFNumberIsFloatType(self) }
+    }
+    ///Returns the value as an `i64`, or `None` if the conversion is lossy (e.g. a fractional float).
+    pub fn as_i64(&self) -> Option<i64> {
+        let mut value: i64 = 0;
+        let exact = unsafe { CFNumberGetValue(self, CFNumberType::SInt64Type, &mut value as *mut i64 as *mut c_void) };
+        exact.then_some(value)
+    }
+    ///Returns the value as an `f64`, or `None` if the conversion is lossy (e.g. a very large integer).
+    pub fn as_f64(&self) -> Option<f64> {
+        let mut value: f64 = 0.0;
+        let exact = unsafe { CFNumberGetValue(self, CFNumberType::Float64Type, &mut value as *mut f64 as *mut c_void) };
+        exact.then_some(value)
+    }
+}
+
+#[test] fn number() {
+    let number = CFNumber::from_i64(42);
+    assert_eq!(number.as_i64(), Some(42));
+    assert_eq!(number.as_f64(), Some(42.0));
+    assert!(!number.is_float_type());
+    let number = CFNumber::from_f64(1.5);
+    assert_eq!(number.as_f64(), Some(1.5));
+    assert_eq!(number.as_i64(), None);
+    assert!(CFBoolean::from_bool(true).value());
+    assert!(!CFBoolean::from_bool(false).value());
+}
+
+#[test] fn debug() {
+    use crate::{CFTypeBehavior, CFTypeWithBaseType};
+    let number = CFNumber::from_i64(42);
+    assert_eq!(format!("{:?}", &*number), number.description().as_string());
+    assert_eq!(CFTypeBehavior::type_id(&*number), <CFNumber as CFTypeWithBaseType>::type_id());
+}
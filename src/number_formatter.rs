@@ -0,0 +1,79 @@
+//!<CoreFoundation/CFNumberFormatter.h>
+
+use std::ffi::c_void;
+use crate::base::{CFAllocator, CFIndex, CFRange};
+use crate::cf_type;
+use crate::cell::StrongCell;
+use crate::ownership::CreateRule;
+use crate::locale::CFLocale;
+use crate::number::CFNumberType;
+use crate::CFString;
+
+#[repr(transparent)]
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub struct CFNumberFormatterStyle(CFIndex);
+#[allow(non_upper_case_globals)]
+impl CFNumberFormatterStyle {
+    pub const NoStyle: CFNumberFormatterStyle = CFNumberFormatterStyle(0);
+    pub const Decimal: CFNumberFormatterStyle = CFNumberFormatterStyle(1);
+    pub const Currency: CFNumberFormatterStyle = CFNumberFormatterStyle(2);
+    pub const Percent: CFNumberFormatterStyle = CFNumberFormatterStyle(3);
+    pub const Scientific: CFNumberFormatterStyle = CFNumberFormatterStyle(4);
+    pub const SpellOut: CFNumberFormatterStyle = CFNumberFormatterStyle(5);
+}
+
+cf_type!(pub struct CFNumberFormatter: CFNumberFormatterGetTypeID);
+
+extern "C" {
+    fn CFNumberFormatterCreate(allocator: *const CFAllocator, locale: *const CFLocale, style: CFNumberFormatterStyle) -> CreateRule<CFNumberFormatter>;
+    fn CFNumberFormatterGetValueFromString(formatter: *const CFNumberFormatter, string: *const CFString, rangep: *mut CFRange, numberType: CFNumberType, valuePtr: *mut c_void) -> bool;
+}
+
+impl CFNumberFormatter {
+    ///Creates a formatter for the given locale and style.  See cocoa docs for `CFNumberFormatterCreate`.
+    ///
+    /// # Panics
+    /// If CF fails to allocate the object, see [allocation failure](crate#allocation-failure).
+    pub fn new(locale: &CFLocale, style: CFNumberFormatterStyle) -> StrongCell<CFNumberFormatter> {
+        Self::new_in(locale, style, None)
+    }
+    ///Like [Self::new], using `allocator`, or the default allocator if `None`.
+    pub fn new_in(locale: &CFLocale, style: CFNumberFormatterStyle, allocator: Option<&CFAllocator>) -> StrongCell<CFNumberFormatter> {
+        unsafe {
+            let raw = CFNumberFormatterCreate(CFAllocator::ptr_or_default(allocator), locale, style);
+            raw.into_cell().expect("CFNumberFormatterCreate returned NULL")
+        }
+    }
+    ///Parses the entire string.
+    ///
+    /// Returns `false` if the string isn't a number in this format, or if only a prefix of it is.
+    ///
+    /// # Safety
+    /// `value` must be valid for writes of `number_type`.
+    unsafe fn value_from_string(&self, string: &CFString, number_type: CFNumberType, value: *mut c_void) -> bool {
+        let length = string.length();
+        let mut range = CFRange { location: 0, length };
+        let ok = CFNumberFormatterGetValueFromString(self, string, &mut range, number_type, value);
+        ok && range.location == 0 && range.length == length
+    }
+    ///Parses the entire string as a floating-point number.  See cocoa docs for `CFNumberFormatterGetValueFromString`.
+    pub fn parse_f64(&self, string: &CFString) -> Option<f64> {
+        let mut value: f64 = 0.0;
+        let ok = unsafe { self.value_from_string(string, CFNumberType::Float64Type, &mut value as *mut f64 as *mut c_void) };
+        ok.then_some(value)
+    }
+    ///Parses the entire string as an integer.  See cocoa docs for `CFNumberFormatterGetValueFromString`.
+    pub fn parse_i64(&self, string: &CFString) -> Option<i64> {
+        let mut value: i64 = 0;
+        let ok = unsafe { self.value_from_string(string, CFNumberType::SInt64Type, &mut value as *mut i64 as *mut c_void) };
+        ok.then_some(value)
+    }
+}
+
+#[test] fn parse_de_de() {
+    let locale = CFLocale::from_identifier(&CFString::from_str("de_DE"));
+    let formatter = CFNumberFormatter::new(&locale, CFNumberFormatterStyle::Decimal);
+    assert_eq!(formatter.parse_f64(&CFString::from_str("1.234,5")), Some(1234.5));
+    assert_eq!(formatter.parse_i64(&CFString::from_str("1.234")), Some(1234));
+    assert_eq!(formatter.parse_f64(&CFString::from_str("1.234,5 apples")), None);
+}
@@ -0,0 +1,105 @@
+/*!
+Return types for `extern` declarations that encode CF ownership.
+
+CF functions follow one of two conventions:
+* The "Create rule": functions with `Create` or `Copy` in the name return an object the caller owns.
+  Declare these as returning [CreateRule].
+* The "Get rule": other functions return an object owned by something else, usually an argument.
+  Declare these as returning [GetRule], with a lifetime tied to the owning argument.
+
+```
+use core_foundationr::{CFString, CreateRule, GetRule, CFLocale};
+extern "C" {
+    fn CFLocaleCopyCurrent() -> CreateRule<CFLocale>;
+    fn CFLocaleGetIdentifier<'a>(locale: &'a CFLocale) -> GetRule<'a, CFString>;
+}
+let locale = unsafe{ CFLocaleCopyCurrent() }.into_cell().unwrap();
+let identifier: &CFString = unsafe{ CFLocaleGetIdentifier(&locale) }.get().unwrap();
+```
+
+Both types are transparent wrappers around the pointer, so they're FFI-safe.  Neither can be constructed
+from Rust, so the only way to obtain one is from the `extern` declaration, and the ownership rule is checked
+once, where the function is declared, instead of at every call.
+*/
+
+use std::marker::PhantomData;
+use std::mem::forget;
+use crate::base::{CFType, CFShared};
+use crate::cell::StrongCell;
+
+///An object returned by a function following the "Create rule".  See the [module docs](self).
+///
+/// If this is dropped without calling [Self::into_cell], the object is released.
+#[repr(transparent)]
+#[must_use]
+pub struct CreateRule<T: CFType>(*const T);
+impl<T: CFType> CreateRule<T> {
+    ///Takes ownership of the object.  Returns `None` if the function returned NULL.
+    pub fn into_cell(self) -> Option<StrongCell<T>> {
+        let ptr = self.0;
+        forget(self);
+        unsafe{ StrongCell::from_create(ptr) }
+    }
+    /**
+    Reinterprets the object as type `R`, for example to add an element type to a `CFArray`.
+
+    # Safety
+    The object must be of type `R`.
+    */
+    pub unsafe fn assume_type<R: CFType>(self) -> CreateRule<R> {
+        let ptr = self.0;
+        forget(self);
+        CreateRule(ptr as *const R)
+    }
+}
+impl<T: CFType> Drop for CreateRule<T> {
+    fn drop(&mut self) {
+        drop(unsafe{ StrongCell::from_create(self.0) })
+    }
+}
+
+///An object returned by a function following the "Get rule", valid for `'a`.  See the [module docs](self).
+#[repr(transparent)]
+#[must_use]
+pub struct GetRule<'a, T: CFType>(*const T, PhantomData<&'a T>);
+impl<'a, T: CFType> GetRule<'a, T> {
+    ///Borrows the object for `'a`.  Returns `None` if the function returned NULL.
+    pub fn get(self) -> Option<&'a T> {
+        unsafe{ self.0.as_ref() }
+    }
+    ///Retains the object, so it can outlive `'a`.  Returns `None` if the function returned NULL.
+    pub fn retain(self) -> Option<StrongCell<T>> where T: CFShared {
+        unsafe{ StrongCell::from_get(self.0) }
+    }
+    /**
+    Reinterprets the object as type `R`, for example to narrow `CFTypeAny` to an element type.
+
+    # Safety
+    The object must be of type `R`.
+    */
+    pub unsafe fn assume_type<R: CFType>(self) -> GetRule<'a, R> {
+        GetRule(self.0 as *const R, PhantomData)
+    }
+}
+
+#[cfg(test)] mod tests {
+    use crate::{CFString, CFTypeBehavior};
+    use super::{CreateRule, GetRule};
+
+    extern "C" {
+        fn CFStringCreateCopy(alloc: *const crate::CFAllocator, theString: &CFString) -> CreateRule<CFString>;
+        fn CFLocaleGetSystem() -> GetRule<'static, crate::CFLocale>;
+    }
+
+    #[test] fn rules() {
+        let string = CFString::from_str("a string that is long enough not to be tagged");
+        let copy = unsafe{ CFStringCreateCopy(std::ptr::null(), &string) }.into_cell().unwrap();
+        assert_eq!(copy.as_string(), string.as_string());
+        //dropping without taking ownership releases
+        drop(unsafe{ CFStringCreateCopy(std::ptr::null(), &string) });
+
+        let system = unsafe{ CFLocaleGetSystem() };
+        let retained = system.retain().unwrap();
+        assert!(retained.retain_count() > 1);
+    }
+}
@@ -1,7 +1,9 @@
-use crate::base::{CFType, CFOptionFlags, CFAllocator, CFIndex, OpaqueCType};
+use crate::base::{CFType, CFShared, CFTypeAny, CFOptionFlags, CFAllocator, CFIndex, OpaqueCType};
+use crate::base::{cf_eq_hash, cf_thread_safe, cf_subtype};
 use crate::data::CFData;
 use crate::error::CFError;
 use crate::cell::StrongCell;
+use crate::ownership::CreateRule;
 use crate::CFTypeBehavior;
 #[repr(transparent)]
 pub struct MutabilityOptions(CFOptionFlags);
@@ -24,23 +26,31 @@ impl Format {
 #[repr(C)]
 pub struct CFPropertyList(OpaqueCType);
 impl CFType for CFPropertyList {}
+unsafe impl CFShared for CFPropertyList {}
+cf_eq_hash!(CFPropertyList);
+cf_thread_safe!(CFPropertyList);
+cf_subtype!(CFPropertyList => CFTypeAny);
 
 impl CFPropertyList {
     ///Create a property list from the given data.  See cocoa docs for `CFPropertyListCreateWithData`.
     pub fn from_data(data: &CFData) -> Result<StrongCell<CFPropertyList>, StrongCell<CFError>> {
+        Self::from_data_in(data, None)
+    }
+    ///Like [Self::from_data], using `allocator`, or the default allocator if `None`.
+    pub fn from_data_in(data: &CFData, allocator: Option<&CFAllocator>) -> Result<StrongCell<CFPropertyList>, StrongCell<CFError>> {
         let mut err = unsafe{ CFError::from_ptr(std::ptr::null())};
-        let o = unsafe{ CFPropertyListCreateWithData(CFAllocator::null(), data, MutabilityOptions::Immutable, std::ptr::null_mut(), &mut err)};
-        if !err.is_null() {
-            Err(unsafe{ StrongCell::assuming_retained_nonnull(err) })
+        let o = unsafe{ CFPropertyListCreateWithData(CFAllocator::ptr_or_default(allocator), data, MutabilityOptions::Immutable, std::ptr::null_mut(), &mut err)};
+        if let Some(err) = unsafe{ StrongCell::from_create(err) } {
+            Err(err)
         }
         else {
-            Ok(unsafe{ StrongCell::assuming_retained_nonnull(o)})
+            Ok(o.into_cell().expect("CFPropertyListCreateWithData returned NULL without an error"))
         }
     }
 }
 
 extern "C" {
-    fn CFPropertyListCreateWithData(allocator: *const CFAllocator, data: *const CFData, options: MutabilityOptions, format: *mut Format, error: *mut *const CFError) -> *const CFPropertyList;
+    fn CFPropertyListCreateWithData(allocator: *const CFAllocator, data: *const CFData, options: MutabilityOptions, format: *mut Format, error: *mut *const CFError) -> CreateRule<CFPropertyList>;
 
 }
 
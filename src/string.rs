@@ -1,6 +1,16 @@
+use std::ffi::{CString, OsStr};
 use std::fmt::{Debug, Display};
-use crate::base::{CFString, CFAllocator, CFIndex, CFRange};
-use crate::cell::StrongCell;
+use std::os::raw::c_char;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use crate::base::{CFString, CFType, CFSubtype, CFTypeAny, CFMutableCopy, CFAllocator, CFIndex, CFRange, OpaqueCType};
+use crate::base::cf_eq_hash;
+use crate::cell::{StrongCell, StrongMutCell};
+use crate::ownership::CreateRule;
+use crate::CFArray;
+use crate::locale::CFLocale;
+use crate::number_formatter::{CFNumberFormatter, CFNumberFormatterStyle};
 
 #[repr(transparent)]
 #[derive(Debug)]
@@ -27,10 +37,17 @@ impl CFStringEncoding {
 impl CFString {
     ///note: objc knows a faster way for static strings
     ///note: objc knows a faster way for owned strings
+    ///
+    /// # Panics
+    /// If CF fails to allocate the object, see [allocation failure](crate#allocation-failure).
     pub fn from_str(str: &str) -> StrongCell<CFString> {
+        Self::from_str_in(str, None)
+    }
+    ///Like [Self::from_str], using `allocator`, or the default allocator if `None`.
+    pub fn from_str_in(str: &str, allocator: Option<&CFAllocator>) -> StrongCell<CFString> {
         unsafe{
-            let raw = CFStringCreateWithBytes(CFAllocator::null(), str.as_ptr(), str.as_bytes().len() as CFIndex, CFStringEncoding::UTF8, false);
-            StrongCell::assuming_retained_nonnull(raw)
+            let raw = CFStringCreateWithBytes(CFAllocator::ptr_or_default(allocator), str.as_ptr(), str.as_bytes().len() as CFIndex, CFStringEncoding::UTF8, false);
+            raw.into_cell().expect("CFStringCreateWithBytes returned NULL")
         }
     }
     ///Returns the length of the string, see cocoa docs for `CFStringGetLength`.
@@ -53,6 +70,131 @@ impl CFString {
         std::mem::forget(string);
         unsafe{ String::from_raw_parts(mut_ptr, used_buf_len as usize, actual_capaicty)}
     }
+    ///Returns the integer value of the string, see cocoa docs for `CFStringGetIntValue`.
+    ///
+    /// Parsing stops at the first non-numeric character, and 0 is returned if there is no number.
+    /// To detect errors, use [Self::parse].
+    pub fn int_value(&self) -> i32 {
+        unsafe { CFStringGetIntValue(self) }
+    }
+    ///Returns the double value of the string, see cocoa docs for `CFStringGetDoubleValue`.
+    ///
+    /// Parsing stops at the first non-numeric character, and 0.0 is returned if there is no number.
+    /// To detect errors, use [Self::parse].
+    pub fn double_value(&self) -> f64 {
+        unsafe { CFStringGetDoubleValue(self) }
+    }
+    ///Strictly parses the whole string as `T`, using [FromStr].
+    pub fn parse<T: FromStr>(&self) -> Result<T, T::Err> {
+        self.as_string().parse()
+    }
+    ///Strictly parses the whole string as a decimal number in the conventions of `locale`,
+    /// e.g. "1.234,5" in `de_DE`.
+    ///
+    /// Returns `None` if the string is not a number in that locale.  To parse many strings,
+    /// create a [CFNumberFormatter] once and reuse it.
+    pub fn double_value_in_locale(&self, locale: &CFLocale) -> Option<f64> {
+        CFNumberFormatter::new(locale, CFNumberFormatterStyle::Decimal).parse_f64(self)
+    }
+    ///Returns the substring in `range`.  See cocoa docs for `CFStringCreateWithSubstring`.
+    ///
+    /// # Panics
+    /// If `range` is not within the string, or if CF fails to allocate the object, see [allocation failure](crate#allocation-failure).
+    pub fn substring(&self, range: CFRange) -> StrongCell<CFString> {
+        self.substring_in(range, None)
+    }
+    ///Like [Self::substring], using `allocator`, or the default allocator if `None`.
+    pub fn substring_in(&self, range: CFRange, allocator: Option<&CFAllocator>) -> StrongCell<CFString> {
+        range.assert_in_bounds(self.length());
+        unsafe {
+            let raw = CFStringCreateWithSubstring(CFAllocator::ptr_or_default(allocator), self, range);
+            raw.into_cell().expect("CFStringCreateWithSubstring returned NULL")
+        }
+    }
+    ///Splits the string on each occurrence of `separator`.  See cocoa docs for `CFStringCreateArrayBySeparatingStrings`.
+    ///
+    /// # Panics
+    /// If CF fails to allocate the object, see [allocation failure](crate#allocation-failure).
+    pub fn split(&self, separator: &CFString) -> StrongCell<CFArray<CFString>> {
+        self.split_in(separator, None)
+    }
+    ///Like [Self::split], using `allocator`, or the default allocator if `None`.
+    pub fn split_in(&self, separator: &CFString, allocator: Option<&CFAllocator>) -> StrongCell<CFArray<CFString>> {
+        unsafe {
+            let raw = CFStringCreateArrayBySeparatingStrings(CFAllocator::ptr_or_default(allocator), self, separator);
+            raw.assume_type::<CFArray<CFString>>().into_cell().expect("CFStringCreateArrayBySeparatingStrings returned NULL")
+        }
+    }
+    ///Joins the strings in `array`, placing `separator` between each.  See cocoa docs for `CFStringCreateByCombiningStrings`.
+    ///
+    /// # Panics
+    /// If CF fails to allocate the object, see [allocation failure](crate#allocation-failure).
+    pub fn join(array: &CFArray<CFString>, separator: &CFString) -> StrongCell<CFString> {
+        Self::join_in(array, separator, None)
+    }
+    ///Like [Self::join], using `allocator`, or the default allocator if `None`.
+    pub fn join_in(array: &CFArray<CFString>, separator: &CFString, allocator: Option<&CFAllocator>) -> StrongCell<CFString> {
+        unsafe {
+            let raw = CFStringCreateByCombiningStrings(CFAllocator::ptr_or_default(allocator), array.as_untyped(), separator);
+            raw.into_cell().expect("CFStringCreateByCombiningStrings returned NULL")
+        }
+    }
+    ///Creates a string from the file system representation of `path`.  See cocoa docs for `CFStringCreateWithFileSystemRepresentation`.
+    ///
+    /// Returns `None` if the path contains an interior NUL, or if its bytes can't be interpreted in the
+    /// file system encoding (UTF-8 on macOS).
+    pub fn from_path(path: &Path) -> Option<StrongCell<CFString>> {
+        CFString::from_os_str(path.as_os_str())
+    }
+    ///Like [Self::from_path], using `allocator`, or the default allocator if `None`.
+    pub fn from_path_in(path: &Path, allocator: Option<&CFAllocator>) -> Option<StrongCell<CFString>> {
+        CFString::from_os_str_in(path.as_os_str(), allocator)
+    }
+    ///Like [Self::from_path], for an arbitrary [OsStr].
+    pub fn from_os_str(os_str: &OsStr) -> Option<StrongCell<CFString>> {
+        Self::from_os_str_in(os_str, None)
+    }
+    ///Like [Self::from_os_str], using `allocator`, or the default allocator if `None`.
+    pub fn from_os_str_in(os_str: &OsStr, allocator: Option<&CFAllocator>) -> Option<StrongCell<CFString>> {
+        let c_string = CString::new(os_str.as_bytes()).ok()?;
+        unsafe {
+            let raw = CFStringCreateWithFileSystemRepresentation(CFAllocator::ptr_or_default(allocator), c_string.as_ptr());
+            raw.into_cell()
+        }
+    }
+    ///Converts to a path using the file system representation.  See cocoa docs for `CFStringGetFileSystemRepresentation`.
+    ///
+    /// Note that the file system representation is decomposed Unicode, so e.g. "é" (U+00E9) is
+    /// represented as "e" followed by U+0301.
+    ///
+    /// Returns `None` if the string can't be represented in the file system encoding.
+    pub fn to_path_buf(&self) -> Option<PathBuf> {
+        let max_size = unsafe{ CFStringGetMaximumSizeOfFileSystemRepresentation(self) };
+        let mut buffer: Vec<u8> = vec![0; max_size as usize];
+        let ok = unsafe{ CFStringGetFileSystemRepresentation(self, buffer.as_mut_ptr() as *mut c_char, max_size) };
+        if !ok {
+            return None;
+        }
+        let nul = buffer.iter().position(|b| *b == 0).unwrap_or(buffer.len());
+        buffer.truncate(nul);
+        Some(PathBuf::from(OsStr::from_bytes(&buffer)))
+    }
+}
+///Converts via the file system representation, see [CFString::from_os_str].
+///
+/// Bytes that can't be represented are replaced with U+FFFD, as with [OsStr::to_string_lossy].
+impl From<&OsStr> for StrongCell<CFString> {
+    fn from(os_str: &OsStr) -> Self {
+        CFString::from_os_str(os_str).unwrap_or_else(|| CFString::from_str(&os_str.to_string_lossy()))
+    }
+}
+///Converts via the file system representation, see [CFString::from_path].
+///
+/// Bytes that can't be represented are replaced with U+FFFD, as with [Path::to_string_lossy].
+impl From<&Path> for StrongCell<CFString> {
+    fn from(path: &Path) -> Self {
+        path.as_os_str().into()
+    }
 }
 impl Debug for CFString {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -65,13 +207,93 @@ impl Display for CFString {
     }
 }
 
+impl CFMutableCopy for CFString {
+    type Mutable = CFMutableString;
+    fn mutable_copy_in(&self, allocator: Option<&CFAllocator>) -> StrongMutCell<CFMutableString> {
+        unsafe {
+            let raw = CFStringCreateMutableCopy(CFAllocator::ptr_or_default(allocator), 0, self);
+            raw.into_cell().expect("CFStringCreateMutableCopy returned NULL").assuming_mut()
+        }
+    }
+}
+
+///A mutable string.  See cocoa docs for `CFMutableStringRef`.
+///
+/// Mutating methods take `&mut self`, so these are usually held in a [StrongMutCell].
+#[repr(C)]
+pub struct CFMutableString(OpaqueCType);
+impl CFType for CFMutableString {}
+cf_eq_hash!(CFMutableString);
+unsafe impl CFSubtype<CFString> for CFMutableString {}
+unsafe impl CFSubtype<CFTypeAny> for CFMutableString {}
+//may be moved between threads, but mutation isn't synchronized
+unsafe impl Send for CFMutableString {}
+impl CFMutableString {
+    //the result must not escape the borrow, see CFShared
+    fn as_immutable(&self) -> &CFString {
+        unsafe{ &*(self as *const CFMutableString as *const CFString) }
+    }
+    ///Creates an empty mutable string, see cocoa docs for `CFStringCreateMutable`.
+    ///
+    /// # Panics
+    /// If CF fails to allocate the object, see [allocation failure](crate#allocation-failure).
+    pub fn new() -> StrongMutCell<CFMutableString> {
+        Self::new_in(None)
+    }
+    ///Like [Self::new], using `allocator`, or the default allocator if `None`.
+    pub fn new_in(allocator: Option<&CFAllocator>) -> StrongMutCell<CFMutableString> {
+        unsafe {
+            let raw = CFStringCreateMutable(CFAllocator::ptr_or_default(allocator), 0);
+            raw.into_cell().expect("CFStringCreateMutable returned NULL").assuming_mut()
+        }
+    }
+    ///Returns the length of the string, see cocoa docs for `CFStringGetLength`.
+    pub fn length(&self) -> CFIndex {
+        self.as_immutable().length()
+    }
+    ///Copies the contents into a Rust string.
+    pub fn as_string(&self) -> String {
+        self.as_immutable().as_string()
+    }
+    ///Appends `string`, see cocoa docs for `CFStringAppend`.
+    pub fn append(&mut self, string: &CFString) {
+        unsafe { CFStringAppend(self, string) }
+    }
+}
+impl CFMutableCopy for CFMutableString {
+    type Mutable = CFMutableString;
+    fn mutable_copy_in(&self, allocator: Option<&CFAllocator>) -> StrongMutCell<CFMutableString> {
+        self.as_immutable().mutable_copy_in(allocator)
+    }
+}
+impl Debug for CFMutableString {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Debug::fmt(self.as_immutable(), f)
+    }
+}
+impl Display for CFMutableString {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(self.as_immutable(), f)
+    }
+}
 
 #[link(name="CoreFoundation",kind="framework")]
 extern "C" {
-    fn CFStringCreateWithBytes(alloc: *const CFAllocator, bytes: *const u8, numBytes: CFIndex, encoding: CFStringEncoding, isExternalRepresentation: bool ) -> *const CFString;
+    fn CFStringCreateWithBytes(alloc: *const CFAllocator, bytes: *const u8, numBytes: CFIndex, encoding: CFStringEncoding, isExternalRepresentation: bool ) -> CreateRule<CFString>;
     fn CFStringGetBytes(theString: *const CFString, range: CFRange, encoding: CFStringEncoding, lossByte: u8, isExternalRepresentation: bool, buffer: *mut u8, maxBufferLen: CFIndex, usedBufLen: *mut CFIndex) -> CFIndex;
     fn CFStringGetLength(theString: *const CFString) -> CFIndex;
     fn CFStringGetMaximumSizeForEncoding(length: CFIndex, encoding: CFStringEncoding) -> CFIndex;
+    fn CFStringGetIntValue(str: *const CFString) -> i32;
+    fn CFStringGetDoubleValue(str: *const CFString) -> f64;
+    fn CFStringCreateWithSubstring(alloc: *const CFAllocator, str: *const CFString, range: CFRange) -> CreateRule<CFString>;
+    fn CFStringCreateArrayBySeparatingStrings(alloc: *const CFAllocator, theString: *const CFString, separatorString: *const CFString) -> CreateRule<CFArray>;
+    fn CFStringCreateByCombiningStrings(alloc: *const CFAllocator, theArray: *const CFArray, separatorString: *const CFString) -> CreateRule<CFString>;
+    fn CFStringCreateWithFileSystemRepresentation(alloc: *const CFAllocator, buffer: *const c_char) -> CreateRule<CFString>;
+    fn CFStringGetFileSystemRepresentation(string: *const CFString, buffer: *mut c_char, maxBufLen: CFIndex) -> bool;
+    fn CFStringGetMaximumSizeOfFileSystemRepresentation(string: *const CFString) -> CFIndex;
+    fn CFStringCreateMutableCopy(alloc: *const CFAllocator, maxLength: CFIndex, theString: *const CFString) -> CreateRule<CFMutableString>;
+    fn CFStringCreateMutable(alloc: *const CFAllocator, maxLength: CFIndex) -> CreateRule<CFMutableString>;
+    fn CFStringAppend(theString: *const CFMutableString, appendedString: *const CFString);
 
 }
 
@@ -81,4 +303,64 @@ extern "C" {
     println!("cf {:?}",string);
     println!("roundtrip {:?}",string.as_string());
 
-}
\ No newline at end of file
+}
+
+#[test] fn path_roundtrip() {
+    let path = Path::new("/Users/test/café.txt");
+    let string = CFString::from_path(path).unwrap();
+    assert_eq!(string.as_string(), "/Users/test/café.txt");
+    //file system representation is decomposed
+    assert_eq!(string.to_path_buf().unwrap(), Path::new("/Users/test/cafe\u{301}.txt"));
+
+    let invalid = OsStr::from_bytes(b"/tmp/\xff");
+    assert!(CFString::from_os_str(invalid).is_none());
+    let lossy: StrongCell<CFString> = invalid.into();
+    assert_eq!(lossy.as_string(), "/tmp/\u{fffd}");
+}
+
+#[test] fn split_join() {
+    let string = CFString::from_str("a,bc,,d");
+    let separator = CFString::from_str(",");
+    let parts = string.split(&separator);
+    let parts_str: Vec<String> = parts.iter().map(|p| p.as_string()).collect();
+    assert_eq!(parts_str, vec!["a","bc","","d"]);
+    assert_eq!(parts.get(1).unwrap().as_string(), "bc");
+    assert!(parts.get(4).is_none());
+
+    let joined = CFString::join(&parts, &CFString::from_str(" + "));
+    assert_eq!(joined.as_string(), "a + bc +  + d");
+}
+
+#[test] fn substring() {
+    let string = CFString::from_str("hello world");
+    let sub = string.substring(CFRange { location: 6, length: 5 });
+    assert_eq!(sub.as_string(), "world");
+}
+
+#[test] fn numeric_values() {
+    let string = CFString::from_str("42.5");
+    assert_eq!(string.int_value(), 42);
+    assert_eq!(string.double_value(), 42.5);
+    assert_eq!(string.parse::<f64>(), Ok(42.5));
+    assert!(string.parse::<i32>().is_err());
+
+    let german = CFLocale::from_identifier(&CFString::from_str("de_DE"));
+    assert_eq!(CFString::from_str("1.234,5").double_value_in_locale(&german), Some(1234.5));
+    assert_eq!(CFString::from_str("abc").double_value_in_locale(&german), None);
+}
+
+#[test] fn mutable_string() {
+    use crate::CFTypeBehavior;
+    let mut string = CFMutableString::new();
+    string.append(&CFString::from_str("hello, "));
+    string.append(&CFString::from_str("world"));
+    assert_eq!(string.as_string(), "hello, world");
+    let copy = CFString::from_str("hello, ").mutable_copy();
+    assert_eq!(copy.as_string(), "hello, ");
+    assert_eq!(string.length(), 12);
+    assert_eq!(format!("{}", string), "hello, world");
+    //upcasts
+    let immutable: StrongCell<CFString> = string.upcast();
+    assert_eq!(immutable.type_id(), CFString::from_str("").type_id());
+    assert_eq!(immutable.as_string(), "hello, world");
+}
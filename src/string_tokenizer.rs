@@ -0,0 +1,190 @@
+//!<CoreFoundation/CFStringTokenizer.h>
+
+use crate::base::{CFAllocator, CFOptionFlags, CFIndex, CFRange, CFTypeAny};
+use crate::cf_type;
+use crate::cell::{StrongCell, StrongMutCell};
+use crate::ownership::CreateRule;
+use crate::locale::CFLocale;
+use crate::CFString;
+
+///The kind of token the tokenizer produces.  Pass one of these to [CFStringTokenizer::new].
+#[repr(transparent)]
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub struct CFStringTokenizerUnit(CFOptionFlags);
+#[allow(non_upper_case_globals)]
+impl CFStringTokenizerUnit {
+    pub const Word: CFStringTokenizerUnit = CFStringTokenizerUnit(0);
+    pub const Sentence: CFStringTokenizerUnit = CFStringTokenizerUnit(1);
+    pub const Paragraph: CFStringTokenizerUnit = CFStringTokenizerUnit(2);
+    pub const LineBreak: CFStringTokenizerUnit = CFStringTokenizerUnit(3);
+    pub const WordBoundary: CFStringTokenizerUnit = CFStringTokenizerUnit(4);
+}
+
+///Attributes that may be requested for the current token, see [CFStringTokenizer::current_token_attribute].
+#[repr(transparent)]
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub struct CFStringTokenizerAttribute(CFOptionFlags);
+#[allow(non_upper_case_globals)]
+impl CFStringTokenizerAttribute {
+    pub const LatinTranscription: CFStringTokenizerAttribute = CFStringTokenizerAttribute(1 << 16);
+    pub const Language: CFStringTokenizerAttribute = CFStringTokenizerAttribute(1 << 17);
+}
+
+///Describes the token found by [CFStringTokenizer::advance_to_next_token].
+///
+/// This is a bitmask; [Self::None] indicates there are no more tokens.
+#[repr(transparent)]
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub struct CFStringTokenizerTokenType(CFOptionFlags);
+#[allow(non_upper_case_globals)]
+impl CFStringTokenizerTokenType {
+    pub const None: CFStringTokenizerTokenType = CFStringTokenizerTokenType(0);
+    pub const Normal: CFStringTokenizerTokenType = CFStringTokenizerTokenType(1 << 0);
+    pub const HasSubTokensMask: CFStringTokenizerTokenType = CFStringTokenizerTokenType(1 << 1);
+    pub const HasDerivedSubTokensMask: CFStringTokenizerTokenType = CFStringTokenizerTokenType(1 << 2);
+    pub const HasHasNumbersMask: CFStringTokenizerTokenType = CFStringTokenizerTokenType(1 << 3);
+    pub const HasNonLettersMask: CFStringTokenizerTokenType = CFStringTokenizerTokenType(1 << 4);
+    pub const IsCJWordMask: CFStringTokenizerTokenType = CFStringTokenizerTokenType(1 << 5);
+
+    ///Whether all bits of `mask` are set.
+    pub fn contains(&self, mask: CFStringTokenizerTokenType) -> bool {
+        self.0 & mask.0 == mask.0
+    }
+}
+
+cf_type!(pub struct CFStringTokenizer: CFStringTokenizerGetTypeID, unique);
+
+extern "C" {
+    fn CFStringTokenizerCreate(alloc: *const CFAllocator, string: *const CFString, range: CFRange, options: CFOptionFlags, locale: *const CFLocale) -> CreateRule<CFStringTokenizer>;
+    fn CFStringTokenizerSetString(tokenizer: *const CFStringTokenizer, string: *const CFString, range: CFRange);
+    fn CFStringTokenizerGoToTokenAtIndex(tokenizer: *const CFStringTokenizer, index: CFIndex) -> CFStringTokenizerTokenType;
+    fn CFStringTokenizerAdvanceToNextToken(tokenizer: *const CFStringTokenizer) -> CFStringTokenizerTokenType;
+    fn CFStringTokenizerGetCurrentTokenRange(tokenizer: *const CFStringTokenizer) -> CFRange;
+    fn CFStringTokenizerCopyCurrentTokenAttribute(tokenizer: *const CFStringTokenizer, attribute: CFOptionFlags) -> CreateRule<CFTypeAny>;
+    fn CFStringTokenizerCopyBestStringLanguage(string: *const CFString, range: CFRange) -> CreateRule<CFString>;
+}
+
+impl CFStringTokenizer {
+    ///Creates a tokenizer over `range` of `string`.
+    ///
+    /// `attributes` are OR'd into the unit so that they can later be read with [Self::current_token_attribute].
+    /// If `locale` is `None`, the tokenizer uses the current locale.
+    ///
+    /// The tokenizer keeps its position as state, so it is returned as a [StrongMutCell].
+    ///
+    /// # Panics
+    /// If `range` is not within `string`, or if CF fails to allocate the object, see [allocation failure](crate#allocation-failure).
+    pub fn new(string: &CFString, range: CFRange, unit: CFStringTokenizerUnit, attributes: &[CFStringTokenizerAttribute], locale: Option<&CFLocale>) -> StrongMutCell<CFStringTokenizer> {
+        Self::new_in(string, range, unit, attributes, locale, None)
+    }
+    ///Like [Self::new], using `allocator`, or the default allocator if `None`.
+    pub fn new_in(string: &CFString, range: CFRange, unit: CFStringTokenizerUnit, attributes: &[CFStringTokenizerAttribute], locale: Option<&CFLocale>, allocator: Option<&CFAllocator>) -> StrongMutCell<CFStringTokenizer> {
+        range.assert_in_bounds(string.length());
+        let options = attributes.iter().fold(unit.0, |options, attribute| options | attribute.0);
+        let locale = locale.map(|l| l as *const CFLocale).unwrap_or(std::ptr::null());
+        unsafe {
+            let raw = CFStringTokenizerCreate(CFAllocator::ptr_or_default(allocator), string, range, options, locale);
+            raw.into_cell().expect("CFStringTokenizerCreate returned NULL").assuming_mut()
+        }
+    }
+    ///Resets the tokenizer to tokenize `range` of a different string.
+    ///
+    /// # Panics
+    /// If `range` is not within `string`.
+    pub fn set_string(&mut self, string: &CFString, range: CFRange) {
+        range.assert_in_bounds(string.length());
+        unsafe { CFStringTokenizerSetString(self, string, range) }
+    }
+    ///Moves to the token containing the character at `index`.
+    ///
+    /// Returns [CFStringTokenizerTokenType::None] if there is no such token.
+    pub fn go_to_token_at_index(&mut self, index: CFIndex) -> CFStringTokenizerTokenType {
+        unsafe { CFStringTokenizerGoToTokenAtIndex(self, index) }
+    }
+    ///Moves to the next token.
+    ///
+    /// Returns [CFStringTokenizerTokenType::None] if there are no more tokens.
+    pub fn advance_to_next_token(&mut self) -> CFStringTokenizerTokenType {
+        unsafe { CFStringTokenizerAdvanceToNextToken(self) }
+    }
+    ///The range of the current token.  The location is `kCFNotFound` if there is no current token.
+    pub fn current_token_range(&self) -> CFRange {
+        unsafe { CFStringTokenizerGetCurrentTokenRange(self) }
+    }
+    ///Copies an attribute of the current token.
+    ///
+    /// The attribute must have been requested when the tokenizer was created.  Returns `None` if
+    /// there is no current token or the attribute is unavailable.
+    pub fn current_token_attribute(&self, attribute: CFStringTokenizerAttribute) -> Option<StrongCell<CFTypeAny>> {
+        unsafe { CFStringTokenizerCopyCurrentTokenAttribute(self, attribute.0) }.into_cell()
+    }
+    ///Returns an iterator over the ranges of the remaining tokens.
+    pub fn ranges(&mut self) -> CFStringTokenizerRanges<'_> {
+        CFStringTokenizerRanges {
+            tokenizer: self
+        }
+    }
+    ///Guesses the language of `range` of `string`, returning a BCP 47 tag like `en` or `ja`.
+    ///
+    /// See cocoa docs for `CFStringTokenizerCopyBestStringLanguage`.  Returns `None` if the language
+    /// cannot be determined.
+    ///
+    /// # Panics
+    /// If `range` is not within `string`.
+    pub fn best_string_language(string: &CFString, range: CFRange) -> Option<StrongCell<CFString>> {
+        range.assert_in_bounds(string.length());
+        unsafe { CFStringTokenizerCopyBestStringLanguage(string, range) }.into_cell()
+    }
+}
+
+///Iterator type for [CFStringTokenizer::ranges]
+pub struct CFStringTokenizerRanges<'a> {
+    tokenizer: &'a mut CFStringTokenizer
+}
+impl<'a> Iterator for CFStringTokenizerRanges<'a> {
+    type Item = CFRange;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.tokenizer.advance_to_next_token() == CFStringTokenizerTokenType::None {
+            None
+        }
+        else {
+            Some(self.tokenizer.current_token_range())
+        }
+    }
+}
+
+#[cfg(test)] mod tests {
+    use crate::{CFString, CFRange};
+    use crate::locale::CFLocale;
+    use super::{CFStringTokenizer, CFStringTokenizerUnit};
+
+    #[test] fn words_and_sentences() {
+        let string = CFString::from_str("Hello world. Second sentence.");
+        let range = CFRange { location: 0, length: string.length() };
+        let locale = CFLocale::from_identifier(&CFString::from_str("en_US"));
+        let mut words = CFStringTokenizer::new(&string, range, CFStringTokenizerUnit::Word, &[], Some(&locale));
+        let words: Vec<CFRange> = words.ranges().collect();
+        assert_eq!(words, vec![
+            CFRange { location: 0, length: 5 },
+            CFRange { location: 6, length: 5 },
+            CFRange { location: 13, length: 6 },
+            CFRange { location: 20, length: 8 },
+        ]);
+
+        let mut sentences = CFStringTokenizer::new(&string, range, CFStringTokenizerUnit::Sentence, &[], None);
+        assert_eq!(sentences.ranges().count(), 2);
+    }
+
+    #[test] fn language() {
+        let string = CFString::from_str("The quick brown fox jumps over the lazy dog.");
+        let range = CFRange { location: 0, length: string.length() };
+        let language = CFStringTokenizer::best_string_language(&string, range).unwrap();
+        assert_eq!(language.as_string(), "en");
+    }
+
+    #[test] #[should_panic(expected = "out of bounds")] fn range_overflow() {
+        let string = CFString::from_str("Hello world.");
+        CFStringTokenizer::new(&string, CFRange { location: 1, length: crate::CFIndex::MAX }, CFStringTokenizerUnit::Word, &[], None);
+    }
+}
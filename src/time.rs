@@ -0,0 +1,48 @@
+//! Conversions between [CFDate] and [::time::OffsetDateTime], behind the `time` feature.
+
+use ::time::OffsetDateTime;
+use crate::cell::StrongCell;
+use crate::error::DateOutOfRange;
+use crate::CFDate;
+
+///Converts to the nearest [CFDate].  CF stores dates as `f64` seconds, so this is precise to about a microsecond for present-day dates.
+impl From<OffsetDateTime> for StrongCell<CFDate> {
+    fn from(date: OffsetDateTime) -> Self {
+        CFDate::from_unix_timestamp(date.unix_timestamp(), date.nanosecond())
+    }
+}
+
+///Converts to the nearest nanosecond, in UTC.
+impl TryFrom<&CFDate> for OffsetDateTime {
+    type Error = DateOutOfRange;
+
+    fn try_from(date: &CFDate) -> Result<Self, Self::Error> {
+        let out_of_range = DateOutOfRange { absolute_time: date.absolute_time() };
+        let (seconds, nanos) = date.unix_timestamp().ok_or(out_of_range)?;
+        let nanos = seconds as i128 * 1_000_000_000 + nanos as i128;
+        OffsetDateTime::from_unix_timestamp_nanos(nanos).map_err(|_| out_of_range)
+    }
+}
+
+#[cfg(test)] mod tests {
+    use ::time::{Duration, OffsetDateTime, UtcOffset};
+    use crate::{CFDate, StrongCell};
+
+    #[test] fn date_round_trip() {
+        let reference = OffsetDateTime::from_unix_timestamp(978307200).unwrap() + Duration::milliseconds(500);
+        let date: StrongCell<CFDate> = reference.into();
+        assert_eq!(date.absolute_time(), 0.5);
+        assert_eq!(OffsetDateTime::try_from(&*date).unwrap(), reference);
+
+        //present-day dates keep microseconds through CF
+        let summer = OffsetDateTime::from_unix_timestamp(1625142615).unwrap() + Duration::microseconds(123_456);
+        let date: StrongCell<CFDate> = summer.to_offset(UtcOffset::from_hms(2, 0, 0).unwrap()).into();
+        let back = OffsetDateTime::try_from(&*date).unwrap();
+        assert!((back - summer).abs() < Duration::microseconds(1));
+        //and the nearest nanosecond converts back to the same CFDate
+        let again: StrongCell<CFDate> = back.into();
+        assert_eq!(again.absolute_time(), date.absolute_time());
+
+        assert!(OffsetDateTime::try_from(&*CFDate::from_absolute_time(1e300)).is_err());
+    }
+}
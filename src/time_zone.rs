@@ -0,0 +1,129 @@
+//!<CoreFoundation/CFTimeZone.h>
+
+use crate::base::CFAllocator;
+use crate::cell::StrongCell;
+use crate::date::{CFAbsoluteTime, CFTimeInterval};
+use crate::ownership::{CreateRule, GetRule};
+use crate::{cf_type, CFArray, CFString};
+
+cf_type!(
+    ///A time zone, with its offset from GMT and daylight saving rules.  See cocoa docs for `CFTimeZoneRef`.
+    pub struct CFTimeZone: CFTimeZoneGetTypeID, thread_safe
+);
+
+extern "C" {
+    fn CFTimeZoneCopySystem() -> CreateRule<CFTimeZone>;
+    fn CFTimeZoneCopyDefault() -> CreateRule<CFTimeZone>;
+    fn CFTimeZoneCreateWithName(allocator: *const CFAllocator, name: *const CFString, tryAbbrev: bool) -> CreateRule<CFTimeZone>;
+    fn CFTimeZoneCreateWithTimeIntervalFromGMT(allocator: *const CFAllocator, ti: CFTimeInterval) -> CreateRule<CFTimeZone>;
+    fn CFTimeZoneCopyKnownNames() -> CreateRule<CFArray>;
+    fn CFTimeZoneGetName<'a>(tz: &'a CFTimeZone) -> GetRule<'a, CFString>;
+    fn CFTimeZoneGetSecondsFromGMT(tz: *const CFTimeZone, at: CFAbsoluteTime) -> CFTimeInterval;
+    fn CFTimeZoneCopyAbbreviation(tz: *const CFTimeZone, at: CFAbsoluteTime) -> CreateRule<CFString>;
+    fn CFTimeZoneIsDaylightSavingTime(tz: *const CFTimeZone, at: CFAbsoluteTime) -> bool;
+    fn CFTimeZoneGetDaylightSavingTimeOffset(tz: *const CFTimeZone, at: CFAbsoluteTime) -> CFTimeInterval;
+    fn CFTimeZoneGetNextDaylightSavingTimeTransition(tz: *const CFTimeZone, at: CFAbsoluteTime) -> CFAbsoluteTime;
+}
+
+impl CFTimeZone {
+    ///The time zone of the system, see cocoa docs for `CFTimeZoneCopySystem`.
+    ///
+    /// # Panics
+    /// If CF fails to allocate the object, see [allocation failure](crate#allocation-failure).
+    pub fn system() -> StrongCell<CFTimeZone> {
+        unsafe { CFTimeZoneCopySystem() }.into_cell().expect("CFTimeZoneCopySystem returned NULL")
+    }
+    ///The default time zone for the app, which is the system time zone unless it was changed.
+    ///
+    /// See cocoa docs for `CFTimeZoneCopyDefault`.
+    ///
+    /// # Panics
+    /// If CF fails to allocate the object, see [allocation failure](crate#allocation-failure).
+    pub fn default_time_zone() -> StrongCell<CFTimeZone> {
+        unsafe { CFTimeZoneCopyDefault() }.into_cell().expect("CFTimeZoneCopyDefault returned NULL")
+    }
+    ///Creates a time zone from a name like `Europe/Berlin`, or an abbreviation like `PST`.
+    ///
+    /// Returns `None` if the name is unknown.  See cocoa docs for `CFTimeZoneCreateWithName`.
+    pub fn from_name(name: &CFString) -> Option<StrongCell<CFTimeZone>> {
+        Self::from_name_in(name, None)
+    }
+    ///Like [Self::from_name], using `allocator`, or the default allocator if `None`.
+    pub fn from_name_in(name: &CFString, allocator: Option<&CFAllocator>) -> Option<StrongCell<CFTimeZone>> {
+        unsafe { CFTimeZoneCreateWithName(CFAllocator::ptr_or_default(allocator), name, true) }.into_cell()
+    }
+    ///Creates a time zone with a fixed offset from GMT, and no daylight saving time.
+    ///
+    /// Returns `None` if the offset is out of range.  See cocoa docs for `CFTimeZoneCreateWithTimeIntervalFromGMT`.
+    pub fn from_seconds_from_gmt(seconds: CFTimeInterval) -> Option<StrongCell<CFTimeZone>> {
+        Self::from_seconds_from_gmt_in(seconds, None)
+    }
+    ///Like [Self::from_seconds_from_gmt], using `allocator`, or the default allocator if `None`.
+    pub fn from_seconds_from_gmt_in(seconds: CFTimeInterval, allocator: Option<&CFAllocator>) -> Option<StrongCell<CFTimeZone>> {
+        unsafe { CFTimeZoneCreateWithTimeIntervalFromGMT(CFAllocator::ptr_or_default(allocator), seconds) }.into_cell()
+    }
+    ///Names of all the time zones the system knows, see cocoa docs for `CFTimeZoneCopyKnownNames`.
+    ///
+    /// # Panics
+    /// If CF fails to allocate the object, see [allocation failure](crate#allocation-failure).
+    pub fn known_names() -> StrongCell<CFArray<CFString>> {
+        unsafe { CFTimeZoneCopyKnownNames().assume_type::<CFArray<CFString>>() }.into_cell().expect("CFTimeZoneCopyKnownNames returned NULL")
+    }
+    ///The name, like `Europe/Berlin`.  The return value has the lifetime of the receiver.
+    pub fn name(&self) -> &CFString {
+        unsafe { CFTimeZoneGetName(self) }.get().expect("CFTimeZoneGetName returned NULL")
+    }
+    ///Offset from GMT at the given time, including any daylight saving time.
+    pub fn seconds_from_gmt(&self, at: CFAbsoluteTime) -> CFTimeInterval {
+        unsafe { CFTimeZoneGetSecondsFromGMT(self, at) }
+    }
+    ///The abbreviation at the given time, like `GMT+2` or `CEST`, depending on the user's locale.
+    ///
+    /// See cocoa docs for `CFTimeZoneCopyAbbreviation`.
+    pub fn abbreviation(&self, at: CFAbsoluteTime) -> Option<StrongCell<CFString>> {
+        unsafe { CFTimeZoneCopyAbbreviation(self, at) }.into_cell()
+    }
+    ///Whether daylight saving time is in effect at the given time.
+    pub fn is_daylight_saving_time(&self, at: CFAbsoluteTime) -> bool {
+        unsafe { CFTimeZoneIsDaylightSavingTime(self, at) }
+    }
+    ///How much daylight saving time adds to the offset at the given time, or 0.
+    pub fn daylight_saving_time_offset(&self, at: CFAbsoluteTime) -> CFTimeInterval {
+        unsafe { CFTimeZoneGetDaylightSavingTimeOffset(self, at) }
+    }
+    ///The next daylight saving time transition after the given time, or `None` if there are no more.
+    pub fn next_daylight_saving_time_transition(&self, after: CFAbsoluteTime) -> Option<CFAbsoluteTime> {
+        let next = unsafe { CFTimeZoneGetNextDaylightSavingTimeTransition(self, after) };
+        if next == 0.0 { None } else { Some(next) }
+    }
+}
+
+#[cfg(test)] mod tests {
+    use crate::CFString;
+    use super::CFTimeZone;
+
+    #[test] fn berlin() {
+        let berlin = CFTimeZone::from_name(&CFString::from_str("Europe/Berlin")).unwrap();
+        assert_eq!(berlin.name().as_string(), "Europe/Berlin");
+        //2021-01-01 and 2021-07-01, in absolute time
+        let winter = 631152000.0;
+        let summer = 646790400.0;
+        assert_eq!(berlin.seconds_from_gmt(winter), 3600.0);
+        assert_eq!(berlin.seconds_from_gmt(summer), 7200.0);
+        assert!(berlin.is_daylight_saving_time(summer));
+        assert_eq!(berlin.daylight_saving_time_offset(summer), 3600.0);
+        assert!(!berlin.abbreviation(summer).unwrap().as_string().is_empty());
+        let transition = berlin.next_daylight_saving_time_transition(winter).unwrap();
+        assert!(transition > winter && transition < summer);
+
+        assert!(CFTimeZone::from_name(&CFString::from_str("Not/AZone")).is_none());
+        assert!(CFTimeZone::known_names().iter().any(|n| n.as_string() == "Europe/Berlin"));
+    }
+
+    #[test] fn fixed_offset() {
+        let zone = CFTimeZone::from_seconds_from_gmt(-5.0 * 3600.0).unwrap();
+        assert_eq!(zone.seconds_from_gmt(0.0), -18000.0);
+        assert!(!zone.is_daylight_saving_time(0.0));
+        assert!(zone.next_daylight_saving_time_transition(0.0).is_none());
+    }
+}
@@ -0,0 +1,201 @@
+//! Weak references to CF objects, see [WeakCell].
+
+use std::collections::BTreeMap;
+use std::ffi::c_void;
+use std::fmt::{Debug, Formatter};
+use std::ptr::NonNull;
+use std::sync::{Mutex, MutexGuard};
+use crate::base::{CFType, CFThreadSafe, CFIndex};
+use crate::cell::StrongCell;
+
+extern "C" {
+    fn CFRelease(type_ref: *const c_void);
+    fn CFGetRetainCount(type_ref: *const c_void) -> CFIndex;
+}
+
+struct Entry {
+    ///Distinguishes this object from a later object allocated at the same address
+    generation: u64,
+    weak_count: usize,
+}
+struct Registry {
+    entries: BTreeMap<usize, Entry>,
+    next_generation: u64,
+}
+
+const BUCKETS: usize = 256;
+///Objects that have a weak reference, keyed by address and split into buckets so unrelated releases rarely contend
+static REGISTRY: [Mutex<Registry>; BUCKETS] = [const { Mutex::new(Registry { entries: BTreeMap::new(), next_generation: 0 }) }; BUCKETS];
+
+fn registry(ptr: *const c_void) -> MutexGuard<'static, Registry> {
+    //CF objects are 16-byte aligned, so the low bits carry no information
+    let bucket = &REGISTRY[(ptr as usize >> 4) % BUCKETS];
+    //a panic while holding the lock doesn't leave the registry inconsistent
+    bucket.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/**
+Releases an object owned by [StrongCell] or [crate::StrongMutCell], invalidating any weak references
+if this is the last reference.
+
+# Safety
+The caller must own a retain on `ptr`.
+*/
+pub(crate) unsafe fn release(ptr: *const c_void) {
+    //every release takes the lock, so the retain count can't change under a concurrent registration or release
+    let mut registry = registry(ptr);
+    if CFGetRetainCount(ptr) == 1 {
+        registry.entries.remove(&(ptr as usize));
+        //nobody else has a reference to register, and the release may deallocate, which may drop other cells,
+        //so don't hold the lock
+        drop(registry);
+        CFRelease(ptr);
+    }
+    else {
+        //release under the lock, so that a concurrent release can't also see a count above 1
+        CFRelease(ptr);
+    }
+}
+
+/**
+A weak reference to a CF object.
+
+A `WeakCell` doesn't keep the object alive.  While the object lives, [Self::upgrade] returns a new [StrongCell].
+
+```
+use core_foundationr::{CFData, WeakCell};
+let data = CFData::copy_slice(b"cached plist");
+let weak = unsafe{ WeakCell::new(&data) };
+assert!(weak.upgrade().is_some());
+drop(data);
+assert!(weak.upgrade().is_none());
+```
+
+CF has no release callbacks, so `WeakCell` notices the object is gone by observing the final release in
+[StrongCell] and [crate::StrongMutCell].  This is why [Self::new] is unsafe.
+*/
+pub struct WeakCell<T: CFType> {
+    ptr: NonNull<T>,
+    generation: u64,
+}
+unsafe impl<T: CFThreadSafe> Send for WeakCell<T> {}
+unsafe impl<T: CFThreadSafe> Sync for WeakCell<T> {}
+
+impl<T: CFType> WeakCell<T> {
+    /**
+    Creates a weak reference to the object in `cell`.
+
+    # Safety
+    The last reference to the object must be released by dropping a [StrongCell] or [crate::StrongMutCell].
+    For example, if the object is stored into a `CFArray` and the array outlives every cell, the object is freed
+    by the array and the `WeakCell` can't tell.
+    */
+    pub unsafe fn new(cell: &StrongCell<T>) -> Self {
+        let ptr = NonNull::from(&**cell);
+        let mut registry = registry(ptr.as_ptr() as *const c_void);
+        let next_generation = registry.next_generation;
+        let entry = registry.entries.entry(ptr.as_ptr() as usize).or_insert(Entry { generation: next_generation, weak_count: 0 });
+        entry.weak_count += 1;
+        let generation = entry.generation;
+        if generation == next_generation {
+            registry.next_generation += 1;
+        }
+        WeakCell { ptr, generation }
+    }
+    ///Returns a strong reference if the object is still alive.
+    pub fn upgrade(&self) -> Option<StrongCell<T>> {
+        let registry = registry(self.ptr.as_ptr() as *const c_void);
+        match registry.entries.get(&(self.ptr.as_ptr() as usize)) {
+            Some(entry) if entry.generation == self.generation => {
+                //retain under the lock, so the final release can't happen concurrently
+                Some(unsafe{ StrongCell::retain_assuming_nonnull(self.ptr.as_ptr()) })
+            }
+            _ => None
+        }
+    }
+}
+impl<T: CFType> Clone for WeakCell<T> {
+    fn clone(&self) -> Self {
+        let mut registry = registry(self.ptr.as_ptr() as *const c_void);
+        if let Some(entry) = registry.entries.get_mut(&(self.ptr.as_ptr() as usize)) {
+            if entry.generation == self.generation {
+                entry.weak_count += 1;
+            }
+        }
+        WeakCell { ptr: self.ptr, generation: self.generation }
+    }
+}
+impl<T: CFType> Drop for WeakCell<T> {
+    fn drop(&mut self) {
+        let mut registry = registry(self.ptr.as_ptr() as *const c_void);
+        let key = self.ptr.as_ptr() as usize;
+        if let Some(entry) = registry.entries.get_mut(&key) {
+            if entry.generation == self.generation {
+                entry.weak_count -= 1;
+                if entry.weak_count == 0 {
+                    registry.entries.remove(&key);
+                }
+            }
+        }
+    }
+}
+impl<T: CFType> Debug for WeakCell<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("WeakCell<{}>({:?})", std::any::type_name::<T>(), self.ptr))
+    }
+}
+
+#[cfg(test)] mod tests {
+    use crate::{CFData, CFMutableData, CFTypeBehavior, StrongCell};
+    use super::WeakCell;
+
+    #[test] fn upgrade_until_dropped() {
+        let data = CFData::copy_slice(b"weak reference");
+        let weak = unsafe{ WeakCell::new(&data) };
+        let strong = weak.upgrade().unwrap();
+        assert!(StrongCell::ptr_eq(&strong, &data));
+        drop(strong);
+        let clone = weak.clone();
+        assert!(clone.upgrade().is_some());
+        drop(data);
+        assert!(weak.upgrade().is_none());
+        assert!(clone.upgrade().is_none());
+    }
+
+    #[test] fn upcast_mut_cell() {
+        let mut data = CFMutableData::new();
+        data.append(b"no longer mutated");
+        let data: StrongCell<CFData> = data.upcast();
+        let weak = unsafe{ WeakCell::new(&data) };
+        assert_eq!(weak.upgrade().unwrap().as_slice(), b"no longer mutated");
+        //objects without weak references are released as usual alongside ones with them
+        let other = CFData::copy_slice(b"other");
+        drop(other.clone());
+        drop(data);
+        assert!(weak.upgrade().is_none());
+        assert_eq!(other.retain_count(), 1);
+    }
+
+    #[test] fn concurrent_release() {
+        //threads register and upgrade weak references while another drops the last strong one
+        for _ in 0..200 {
+            let data = CFData::copy_slice(b"shared between threads");
+            let threads: Vec<_> = (0..4).map(|_| {
+                let data = data.clone();
+                std::thread::spawn(move || {
+                    let weak = unsafe{ WeakCell::new(&data) };
+                    let other = CFData::copy_slice(b"released alongside");
+                    drop(data);
+                    drop(other);
+                    if let Some(strong) = weak.upgrade() {
+                        assert_eq!(strong.as_slice(), b"shared between threads");
+                    }
+                    weak
+                })
+            }).collect();
+            drop(data);
+            let weaks: Vec<_> = threads.into_iter().map(|t| t.join().unwrap()).collect();
+            assert!(weaks.iter().all(|w| w.upgrade().is_none()));
+        }
+    }
+}